@@ -1,13 +1,38 @@
 extern crate clap;
 use clap::{Arg, App, ArgMatches};
 
+extern crate glob;
+use glob::Pattern;
+
+extern crate log;
+use log::{LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
 use std::io::Read;
 use std::io::Write;
 use std::fs::File;
-
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 extern crate anki_convert;
+use anki_convert::config;
+use anki_convert::config::{Config, Profile};
+use anki_convert::cache::{Cache, Status};
+use anki_convert::chunk;
+use anki_convert::detect;
+use anki_convert::diff;
+use anki_convert::encoding;
+use anki_convert::html_to_ir::pass::Pipeline;
+use anki_convert::html_to_ir::selector::Selector;
+use anki_convert::ir_to_anki_notes;
+use anki_convert::{Output, RenderOptions};
+use anki_convert::media::Media;
 
 fn get_arguments<'a>() -> ArgMatches<'a> {
     App::new("Incremental Reading Convert")
@@ -16,9 +41,166 @@ fn get_arguments<'a>() -> ArgMatches<'a> {
         .about("Converts html files into files reading to be entered \
                 into Anki's HTML editor.")
         .arg(Arg::with_name("filename")
-            .help("The name of the file to convert")
+            .help("The files, or directories with --recursive, to convert. \
+                   Use - to read from stdin")
             .required(true)
+            .multiple(true)
             .index(1))
+        .arg(Arg::with_name("recursive")
+            .short("r")
+            .long("recursive")
+            .help("Converts the files in directories and their subdirectories")
+            .takes_value(false))
+        .arg(Arg::with_name("include")
+            .long("include")
+            .help("Only converts files in directories matching this glob. \
+                   Defaults to html, htm and xhtml files")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("exclude")
+            .long("exclude")
+            .help("Skips files in directories matching this glob")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("from")
+            .long("from")
+            .help("The format of the input, instead of guessing it from the \
+                   file name or contents")
+            .takes_value(true)
+            .possible_values(&["html", "json"]))
+        .arg(Arg::with_name("encoding")
+            .long("encoding")
+            .help("The character encoding of the input, instead of detecting it")
+            .takes_value(true)
+            .validator(|label| match encoding::for_label(&label) {
+                Some(_) => Ok(()),
+                None => Err(format!("Unknown encoding {}", label)),
+            }))
+        .arg(Arg::with_name("keep")
+            .long("keep")
+            .help("Only converts the elements matching this CSS selector")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|selector| Selector::parse(&selector).map(|_| ())))
+        .arg(Arg::with_name("drop")
+            .long("drop")
+            .help("Leaves out the elements matching this CSS selector")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|selector| Selector::parse(&selector).map(|_| ())))
+        .arg(Arg::with_name("to")
+            .long("to")
+            .help("The format to write")
+            .takes_value(true)
+            .possible_values(&["anki",
+                               "anki-notes",
+                               "markdown",
+                               "supermemo",
+                               "mnemosyne",
+                               "json",
+                               "trace"])
+            .conflicts_with_all(&["markdown", "supermemo", "mnemosyne", "json", "trace"]))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .help("The file to write, or - for stdout. Defaults to stdout when \
+                   reading from stdin")
+            .takes_value(true)
+            .conflicts_with("output-dir"))
+        .arg(Arg::with_name("output-dir")
+            .long("output-dir")
+            .help("Writes the converted files into this directory, mirroring \
+                   the layout of the input directories")
+            .takes_value(true))
+        .arg(Arg::with_name("jobs")
+            .short("j")
+            .long("jobs")
+            .help("The number of files to convert at once. Defaults to the \
+                   number of CPUs")
+            .takes_value(true))
+        .arg(Arg::with_name("cloze")
+            .long("cloze")
+            .help("Turns the given kinds of inline elements into clozes")
+            .takes_value(true)
+            .use_delimiter(true)
+            .possible_values(&["strong", "emphasis", "definition", "code"]))
+        .arg(Arg::with_name("cloze-numbering")
+            .long("cloze-numbering")
+            .help("Whether clozes share one card or get a card each")
+            .takes_value(true)
+            .possible_values(&["single", "numbered"]))
+        .arg(Arg::with_name("cloze-max")
+            .long("cloze-max")
+            .help("The maximum number of clozes per note")
+            .takes_value(true))
+        .arg(Arg::with_name("max-sentences")
+            .long("max-sentences")
+            .help("Starts a new note after this many sentences, splitting \
+                   longer paragraphs, for the outputs with a note per section")
+            .takes_value(true))
+        .arg(Arg::with_name("deck")
+            .long("deck")
+            .help("The deck to import the notes into, for --to anki-notes, or \
+                   the category of the Mnemosyne items")
+            .takes_value(true))
+        .arg(Arg::with_name("normalize")
+            .long("normalize")
+            .help("Normalises the text to Unicode NFC, straight quotes or the \
+                   common dashes")
+            .takes_value(true)
+            .use_delimiter(true)
+            .possible_values(&["nfc", "quotes", "dashes"]))
+        .arg(Arg::with_name("markdown")
+            .long("markdown")
+            .help("Writes the reading as Markdown")
+            .takes_value(false))
+        .arg(Arg::with_name("supermemo")
+            .long("supermemo")
+            .help("Writes the reading as SuperMemo Q&A text")
+            .takes_value(false))
+        .arg(Arg::with_name("mnemosyne")
+            .long("mnemosyne")
+            .help("Writes the reading as Mnemosyne XML")
+            .takes_value(false))
+        .arg(Arg::with_name("json")
+            .long("json")
+            .help("Writes the intermediate representation as JSON")
+            .takes_value(false))
+        .arg(Arg::with_name("trace")
+            .long("trace")
+            .help("Writes every block as Markdown along with the line and tag \
+                   of the input it came from. Only lines are known, so blocks \
+                   from the same line of minified HTML share one")
+            .takes_value(false))
+        .arg(Arg::with_name("cache")
+            .long("cache")
+            .help("Remembers the notes written with --to anki-notes in this \
+                   file, so only new and updated notes are written again")
+            .takes_value(true))
+        .arg(Arg::with_name("media")
+            .long("media")
+            .help("Copies the images into this directory, such as Anki's \
+                   collection.media")
+            .takes_value(true))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .help("Reads the profiles from this file instead of the user and \
+                   project config files")
+            .takes_value(true))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .help("Uses this profile from the config instead of the one \
+                   matching each file")
+            .takes_value(true))
+        .arg(Arg::with_name("watch")
+            .long("watch")
+            .help("Keeps running, reconverting the files whenever they or the \
+                   config change and showing how their output changed")
+            .takes_value(false))
         .arg(Arg::with_name("debug")
             .long("debug")
             .takes_value(false)
@@ -26,31 +208,627 @@ fn get_arguments<'a>() -> ArgMatches<'a> {
         .get_matches()
 }
 
+/// The options given on the command line, which take precedence over the
+/// config files.
+fn get_profile(matches: &ArgMatches) -> Profile {
+    let mut profile = Profile::new();
+
+    if let Some(from) = matches.value_of("from") {
+        profile.set_from(from);
+    }
+    if let Some(encoding) = matches.value_of("encoding") {
+        profile.set_encoding(encoding);
+    }
+    for selector in matches.values_of("keep").into_iter().flatten() {
+        profile.add_keep(selector);
+    }
+    for selector in matches.values_of("drop").into_iter().flatten() {
+        profile.add_drop(selector);
+    }
+
+    let to = if matches.is_present("markdown") {
+        Some("markdown")
+    } else if matches.is_present("supermemo") {
+        Some("supermemo")
+    } else if matches.is_present("mnemosyne") {
+        Some("mnemosyne")
+    } else if matches.is_present("json") {
+        Some("json")
+    } else if matches.is_present("trace") {
+        Some("trace")
+    } else {
+        matches.value_of("to")
+    };
+    if let Some(to) = to {
+        profile.set_to(to);
+    }
+
+    for kind in matches.values_of("cloze").into_iter().flatten() {
+        profile.add_cloze(kind);
+    }
+    if let Some(numbering) = matches.value_of("cloze-numbering") {
+        profile.set_cloze_numbering(numbering);
+    }
+    if let Some(max) = matches.value_of("cloze-max") {
+        profile.set_cloze_max(max.parse().expect("cloze-max must be a number."));
+    }
+    if let Some(max) = matches.value_of("max-sentences") {
+        profile.set_max_sentences(max.parse().expect("max-sentences must be a number."));
+    }
+    if let Some(deck) = matches.value_of("deck") {
+        profile.set_deck(deck);
+    }
+    for kind in matches.values_of("normalize").into_iter().flatten() {
+        profile.add_normalize(kind);
+    }
+    if let Some(media) = matches.value_of("media") {
+        profile.set_media(Path::new(media));
+    }
+
+    profile
+}
+
+fn get_config(matches: &ArgMatches) -> Result<Config, String> {
+    match matches.value_of("config") {
+        Some(path) => Config::load(Path::new(path)),
+        None => Config::load_all(&Config::locate()),
+    }
+}
+
+fn get_patterns(matches: &ArgMatches, name: &str) -> Vec<Pattern> {
+    matches.values_of(name)
+        .map(|values| {
+            values.map(|value| {
+                    Pattern::new(value)
+                        .unwrap_or_else(|err| panic!("Invalid glob {}: {}", value, err))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+thread_local! {
+    static CURRENT_FILE: RefCell<PathBuf> = const { RefCell::new(PathBuf::new()) };
+    static WARNINGS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Prints the warnings of the conversion along with the file being converted,
+/// and counts them so they can be included in the summary. Every worker converts one file at a
+/// time, so the counts are kept per thread.
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= LogLevel::Warn && metadata.target().starts_with("anki_convert")
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        WARNINGS.with(|warnings| warnings.set(warnings.get() + 1));
+        CURRENT_FILE.with(|file| {
+            eprintln!("{}: {}: {}",
+                      record.level(),
+                      file.borrow().display(),
+                      record.args());
+        });
+    }
+}
+
+fn init_logger() {
+    log::set_logger(|max_level| {
+            max_level.set(LogLevelFilter::Warn);
+            Box::new(Logger)
+        })
+        .expect("Failed to set up logging.");
+}
+
+/// Where the options for each file come from.
+struct Context {
+    config: Config,
+    profile: Option<String>,
+    cli: Profile,
+    debug: bool,
+    cache: Option<(PathBuf, Mutex<Cache>)>,
+}
+
+impl Context {
+    fn save_cache(&self) {
+        if let Some((ref path, ref cache)) = self.cache {
+            if let Err(err) = cache.lock().unwrap().save(path) {
+                eprintln!("error: Failed to save the cache: {}", err);
+            }
+        }
+    }
+}
+
+struct Settings {
+    from: Option<String>,
+    encoding: Option<String>,
+    pipeline: Pipeline,
+    output: Output,
+    render: RenderOptions,
+    media: Option<PathBuf>,
+}
+
+impl Settings {
+    fn new(profile: &Profile, debug: bool) -> Result<Self, String> {
+        let output = if debug { Output::Debug } else { profile.output()? };
+
+        Ok(Settings {
+            from: profile.from().map(|x| x.to_string()),
+            encoding: profile.encoding().map(|x| x.to_string()),
+            pipeline: profile.pipeline()?,
+            output,
+            render: profile.render_options()?,
+            media: profile.media().map(Path::to_path_buf),
+        })
+    }
+}
+
+/// A file to convert. A path of `-` stands for stdin or stdout. The suffix of
+/// the output format is added to the output unless it was given explicitly.
+struct Job {
+    input: PathBuf,
+    output: PathBuf,
+    suffix: bool,
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+fn output_suffix(output: Output) -> &'static str {
+    match output {
+        Output::Debug => ".out.debug",
+        Output::Markdown => ".md",
+        Output::SuperMemo => ".txt",
+        Output::Mnemosyne => ".xml",
+        Output::Json => ".json",
+        Output::Trace => ".trace.md",
+        Output::Anki => ".out",
+        Output::AnkiNotes => ".tsv",
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+
+    PathBuf::from(name)
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> ::std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<::std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the files to convert and where to write them. Inputs that cannot be
+/// converted are returned as failures.
+fn get_jobs(matches: &ArgMatches) -> (Vec<Job>, Vec<(PathBuf, String)>) {
+    let recursive = matches.is_present("recursive");
+    let include = get_patterns(matches, "include");
+    let exclude = get_patterns(matches, "exclude");
+    let output_dir = matches.value_of("output-dir").map(PathBuf::from);
+    let inputs = matches.values_of("filename").unwrap().map(PathBuf::from).collect::<Vec<_>>();
+
+    let mut jobs = vec![];
+    let mut failures = vec![];
+
+    if let Some(output) = matches.value_of("output").map(PathBuf::from) {
+        if inputs.len() == 1 && !inputs[0].is_dir() {
+            jobs.push(Job {
+                input: inputs[0].clone(),
+                output,
+                suffix: false,
+            });
+        } else {
+            let input = inputs[0].clone();
+            failures.push((input, "--output takes a single input file".to_string()));
+        }
+        return (jobs, failures);
+    }
+
+    for input in inputs {
+        if is_stdio(&input) {
+            let output = input.clone();
+            jobs.push(Job {
+                input,
+                output,
+                suffix: false,
+            });
+            continue;
+        }
+
+        if !input.is_dir() {
+            let output = match output_dir {
+                Some(ref dir) => dir.join(input.file_name().unwrap_or_default()),
+                None => input.clone(),
+            };
+            jobs.push(Job {
+                input,
+                output,
+                suffix: true,
+            });
+            continue;
+        }
+
+        if !recursive {
+            failures.push((input, "Is a directory, use --recursive to convert it".to_string()));
+            continue;
+        }
+
+        let mut files = vec![];
+        if let Err(err) = walk(&input, &mut files) {
+            failures.push((input, format!("Unable to read directory: {}", err)));
+            continue;
+        }
+
+        for file in files {
+            let relative = file.strip_prefix(&input).unwrap_or(&file).to_path_buf();
+
+            let included = if include.is_empty() {
+                let extension = file.extension().and_then(|x| x.to_str()).unwrap_or("");
+                matches!(extension, "html" | "htm" | "xhtml")
+            } else {
+                include.iter().any(|pattern| pattern.matches_path(&relative))
+            };
+            let excluded = exclude.iter().any(|pattern| pattern.matches_path(&relative));
+            if !included || excluded {
+                continue;
+            }
+
+            let output = match output_dir {
+                Some(ref dir) => dir.join(&relative),
+                None => file.clone(),
+            };
+            jobs.push(Job {
+                input: file,
+                output,
+                suffix: true,
+            });
+        }
+    }
+
+    (jobs, failures)
+}
+
+/// A converted file.
+struct Converted {
+    warnings: usize,
+    contents: String,
+}
+
+/// Converts a single file.
+fn convert(job: &Job, context: &Context) -> Result<Converted, String> {
+    CURRENT_FILE.with(|file| *file.borrow_mut() = job.input.clone());
+    WARNINGS.with(|warnings| warnings.set(0));
+
+    let mut bytes = vec![];
+    let read = if is_stdio(&job.input) {
+        io::stdin().read_to_end(&mut bytes)
+    } else {
+        File::open(&job.input).and_then(|mut file| file.read_to_end(&mut bytes))
+    };
+    read.map_err(|err| format!("Unable to read file: {}", err))?;
+
+    // The canonical URL is in the head, so the start of the file is enough.
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(64 * 1024)]);
+    let url = config::canonical_url(&head);
+    let profile = context.config
+        .resolve(context.profile.as_deref(), &job.input, url.as_deref())?
+        .merge(&context.cli);
+    let settings = Settings::new(&profile, context.debug)?;
+
+    let file_type = settings.from
+        .clone()
+        .or_else(|| {
+            detect::file_type(&job.input).filter(|x| anki_convert::is_supported(x))
+        })
+        .or_else(|| detect::sniff(&bytes).map(|x| x.to_string()))
+        .ok_or_else(|| "Unable to tell the format, use --from to give it".to_string())?;
+
+    let contents = encoding::decode(&bytes, settings.encoding.as_deref())?;
+    let doc = anki_convert::convert_ir(&file_type, &contents, &settings.pipeline)?;
+
+    let doc = match settings.media {
+        Some(ref media_dir) => {
+            let base = match job.input.parent() {
+                Some(parent) if !is_stdio(&job.input) => parent,
+                _ => Path::new(""),
+            };
+            Media::new(base, media_dir)
+                .collect(doc)
+                .map_err(|err| format!("Failed to copy images: {}", err))?
+        }
+        None => doc,
+    };
+
+    // The notes get the same ids with and without the cache.
+    let source = chunk::source(&doc, &job.input);
+    let render = settings.render.clone().set_source(&source).build();
+
+    // The notes that are in the cache as they are now are left out, and the
+    // cache is only updated once the rest have been written.
+    let mut cached = None;
+    let new_contents = match (settings.output, &context.cache) {
+        (Output::AnkiNotes, &Some((_, ref cache))) => {
+            let chunks = anki_convert::chunks(doc, &render);
+            let notes = ir_to_anki_notes::notes(chunks, &source);
+            let hashes = notes.iter()
+                .map(|note| (note.id().to_string(), note.hash()))
+                .collect::<Vec<_>>();
+
+            let notes = {
+                let cache = cache.lock().unwrap();
+                notes.into_iter()
+                    .filter(|note| {
+                        cache.status(&source, note.id(), &note.hash()) != Status::Unchanged
+                    })
+                    .collect::<Vec<_>>()
+            };
+            cached = Some((source, hashes));
+
+            ir_to_anki_notes::convert(&notes, render.deck())
+        }
+        (output, _) => anki_convert::render_with(output, doc, &render),
+    };
+
+    let output = if job.suffix {
+        with_suffix(&job.output, output_suffix(settings.output))
+    } else {
+        job.output.clone()
+    };
+
+    if is_stdio(&output) {
+        io::stdout()
+            .write_all(new_contents.as_bytes())
+            .map_err(|err| format!("Failed to write to stdout: {}", err))?;
+    } else {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Failed to create directory: {}", err))?;
+        }
+        File::create(&output)
+            .and_then(|mut file| file.write_all(new_contents.as_bytes()))
+            .map_err(|err| format!("Failed to write to file: {}", err))?;
+    }
+
+    if let (Some((source, hashes)), Some((_, cache))) = (cached, context.cache.as_ref()) {
+        let report = cache.lock().unwrap().update(&source, &hashes);
+        eprintln!("{}: {} new, {} updated, {} unchanged and {} removed notes",
+                  job.input.display(),
+                  report.new,
+                  report.updated,
+                  report.unchanged,
+                  report.removed);
+    }
+
+    Ok(Converted {
+        warnings: WARNINGS.with(|warnings| warnings.get()),
+        contents: new_contents,
+    })
+}
+
+/// Converts the files on a pool of worker threads.
+fn run(jobs: Vec<Job>,
+       context: &Context,
+       workers: usize)
+       -> Vec<(PathBuf, Result<Converted, String>)> {
+    let queue = Mutex::new(jobs.into_iter());
+    let results = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let job = match queue.lock().unwrap().next() {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                let result = convert(&job, context);
+                results.lock().unwrap().push((job.input, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    results
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The config files in use and when they were last modified.
+fn config_files(matches: &ArgMatches) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let paths = match matches.value_of("config") {
+        Some(path) => vec![PathBuf::from(path)],
+        None => Config::locate(),
+    };
+
+    paths.into_iter()
+        .map(|path| {
+            let time = modified(&path);
+            (path, time)
+        })
+        .collect()
+}
+
+/// Converts the files, then checks them and the config for changes twice a
+/// second. Only the files that changed are converted again, or every file when
+/// the config changed, and the difference in their output is printed.
+fn watch(matches: &ArgMatches, mut context: Context, workers: usize) -> ! {
+    let (jobs, _) = get_jobs(matches);
+    if jobs.iter().any(|job| is_stdio(&job.input) || is_stdio(&job.output)) {
+        eprintln!("error: --watch cannot read from stdin or write to stdout");
+        process::exit(1);
+    }
+
+    let mut configs = config_files(matches);
+    let mut inputs: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+    let mut outputs: HashMap<PathBuf, String> = HashMap::new();
+    let mut failed_jobs = vec![];
+    let mut first = true;
+
+    loop {
+        let current = config_files(matches);
+        if current != configs {
+            configs = current;
+            match get_config(matches) {
+                Ok(config) => {
+                    eprintln!("Config changed, converting every file.");
+                    context.config = config;
+                    inputs.clear();
+                }
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+
+        let (jobs, failures) = get_jobs(matches);
+        if failures != failed_jobs {
+            for (input, err) in &failures {
+                eprintln!("  failed: {}: {}", input.display(), err);
+            }
+            failed_jobs = failures;
+        }
+
+        let changed = jobs.into_iter()
+            .filter(|job| {
+                let time = modified(&job.input);
+                inputs.insert(job.input.clone(), time) != Some(time)
+            })
+            .collect::<Vec<_>>();
+
+        if !changed.is_empty() {
+            let total = changed.len();
+            let (mut updated, mut failed) = (0, 0);
+
+            for (input, result) in run(changed, &context, workers) {
+                let converted = match result {
+                    Ok(converted) => converted,
+                    Err(err) => {
+                        eprintln!("  failed: {}: {}", input.display(), err);
+                        failed += 1;
+                        continue;
+                    }
+                };
+
+                if let Some(old) = outputs.insert(input.clone(), converted.contents) {
+                    let diff = diff::unified(&old, &outputs[&input], 2);
+                    if !diff.is_empty() {
+                        println!("--- {}\n{}", input.display(), diff);
+                        updated += 1;
+                    }
+                }
+            }
+
+            context.save_cache();
+
+            if first {
+                eprintln!("Converted {} files, {} failed. Watching for changes.", total, failed);
+            } else {
+                eprintln!("Converted {} files, {} with new output, {} failed.",
+                          total,
+                          updated,
+                          failed);
+            }
+            first = false;
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
 fn main() {
     let matches = get_arguments();
+    init_logger();
 
-    let filename = matches.value_of("filename").unwrap();
-    let file_path = &Path::new(filename);
-    let mut file = File::open(file_path).expect("Unable to open file.");
+    let config = get_config(&matches).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+    let profile = matches.value_of("profile").map(|x| x.to_string());
+    if let Some(ref name) = profile {
+        if config.profile(name).is_none() {
+            eprintln!("error: Unknown profile {}", name);
+            process::exit(1);
+        }
+    }
 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).expect("Unable to read file.");
+    let cache = matches.value_of("cache").map(|path| {
+        let path = PathBuf::from(path);
+        let cache = Cache::load(&path).unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        });
 
-    let file_type = filename.split('.')
-        .last()
-        .expect("Failed to get filetype.");
+        (path, Mutex::new(cache))
+    });
 
-    let debug = matches.is_present("debug");
-    let new_contents = anki_convert::convert_file(debug, &file_type, &contents);
+    let context = Context {
+        config,
+        profile,
+        cli: get_profile(&matches),
+        debug: matches.is_present("debug"),
+        cache,
+    };
 
-    let output_filename = if debug {
-        format!("{}.out.debug", filename.to_string())
-    } else {
-        format!("{}.out", filename.to_string())
+    let workers = match matches.value_of("jobs") {
+        Some(jobs) => jobs.parse().expect("jobs must be a number."),
+        None => thread::available_parallelism().map(|x| x.get()).unwrap_or(1),
     };
-    let output_file_path = &Path::new(&output_filename);
-    let mut output_file = File::create(output_file_path).expect("Failed to create file.");
 
-    output_file.write_all(&new_contents.into_bytes().as_slice())
-        .expect("Failed to write to file.");
+    if matches.is_present("watch") {
+        watch(&matches, context, workers.max(1));
+    }
+
+    let (jobs, mut failures) = get_jobs(&matches);
+    let total = jobs.len() + failures.len();
+
+    let mut warnings = vec![];
+    for (input, result) in run(jobs, &context, workers.max(1)) {
+        match result {
+            Ok(Converted { warnings: 0, .. }) => (),
+            Ok(converted) => warnings.push((input, converted.warnings)),
+            Err(err) => failures.push((input, err)),
+        }
+    }
+    context.save_cache();
+
+    // A single file is reported through its warnings alone, to keep quiet in
+    // pipelines.
+    if total > 1 {
+        eprintln!("Converted {} of {} files, {} with warnings, {} failed.",
+                  total - failures.len(),
+                  total,
+                  warnings.len(),
+                  failures.len());
+        for (input, count) in &warnings {
+            eprintln!("  warnings: {}: {}", input.display(), count);
+        }
+    }
+    for (input, err) in &failures {
+        eprintln!("  failed: {}: {}", input.display(), err);
+    }
+
+    if !failures.is_empty() {
+        process::exit(1);
+    }
 }
@@ -7,7 +7,9 @@ use std::fs::File;
 use std::path::Path;
 
 extern crate anki_convert;
-use anki_convert::group_lines::group_lines_file;
+use anki_convert::config::{Config, Profile};
+use anki_convert::encoding;
+use anki_convert::group_lines::{group_lines_file_with, Options};
 
 fn get_arguments<'a>() -> ArgMatches<'a> {
     App::new("group_lines")
@@ -18,24 +20,75 @@ fn get_arguments<'a>() -> ArgMatches<'a> {
             .help("The path to the file to convert")
             .required(true)
             .index(1))
+        .arg(Arg::with_name("encoding")
+            .long("encoding")
+            .help("The character encoding of the file, instead of detecting it")
+            .takes_value(true))
+        .arg(Arg::with_name("sentence-per-line")
+            .long("sentence-per-line")
+            .help("Puts every sentence on a line of its own")
+            .takes_value(false))
+        .arg(Arg::with_name("max-sentences")
+            .long("max-sentences")
+            .help("Splits paragraphs after this many sentences")
+            .takes_value(true))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .help("Reads the profiles from this file instead of the user and \
+                   project config files")
+            .takes_value(true))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .help("Uses this profile from the config instead of the one \
+                   matching the file")
+            .takes_value(true))
         .get_matches()
 }
 
+fn get_profile(arguments: &ArgMatches, file_path: &Path) -> Profile {
+    let config = match arguments.value_of("config") {
+        Some(path) => Config::load(Path::new(path)),
+        None => Config::load_all(&Config::locate()),
+    };
+    let profile = config.and_then(|config| {
+            config.resolve(arguments.value_of("profile"), file_path, None)
+        })
+        .expect("Failed to read the config.");
+
+    let mut cli = Profile::new();
+    if let Some(encoding) = arguments.value_of("encoding") {
+        cli.set_encoding(encoding);
+    }
+    if let Some(max) = arguments.value_of("max-sentences") {
+        cli.set_max_sentences(max.parse().expect("max-sentences must be a number."));
+    }
+
+    profile.merge(&cli)
+}
+
 fn main() {
     let arguments = get_arguments();
 
     let filename = arguments.value_of("filename").unwrap();
     let file_path = &Path::new(filename);
+    let profile = get_profile(&arguments, file_path);
     let mut file = File::open(file_path).expect("Failed to open file.");
 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).expect("Failed to read file.");
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).expect("Failed to read file.");
+    let contents = encoding::decode(&bytes, profile.encoding()).expect("Failed to read file.");
 
     let output_filename = filename.to_string() + ".out";
     let output_file_path = &Path::new(&output_filename);
     let mut output_file = File::create(output_file_path).expect("Failed to create file.");
 
-    let new_contents = group_lines_file(&contents);
+    let mut options = Options::new();
+    options.set_sentence_per_line(arguments.is_present("sentence-per-line"));
+    if let Some(max) = profile.max_sentences() {
+        options.set_max_sentences(max);
+    }
+
+    let new_contents = group_lines_file_with(&contents, &options);
 
     output_file.write_all(&new_contents.into_bytes().as_slice())
         .expect("Failed to write to file.");
@@ -1,28 +1,175 @@
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+use std::path::Path;
 
 pub mod group_lines;
 pub mod ir_to_anki;
+pub mod ir_to_anki_notes;
+pub mod ir_to_markdown;
+pub mod ir_to_supermemo;
+pub mod ir_to_mnemosyne;
+pub mod ir_json;
+pub mod ir_to_trace;
 pub mod html_to_ir;
 pub mod ir;
+pub mod cloze;
+pub mod chunk;
+pub mod cache;
+pub mod hash;
+pub mod media;
+pub mod detect;
+pub mod encoding;
+pub mod config;
+pub mod diff;
+
+/// The formats a converted document can be written in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Output {
+    Anki,
+    AnkiNotes,
+    Markdown,
+    SuperMemo,
+    Mnemosyne,
+    Json,
+    Trace,
+    Debug,
+}
+
+/// Whether files of the type can be converted.
+pub fn is_supported(file_type: &str) -> bool {
+    matches!(file_type, "html" | "htm" | "xhtml" | "json")
+}
 
-fn convert_file_debug(file_type: &str, contents: &str) -> String {
+pub fn convert_ir(file_type: &str,
+                  contents: &str,
+                  pipeline: &html_to_ir::pass::Pipeline)
+                  -> Result<ir::Document, String> {
     match file_type {
-        "html" | "xhtml" => format!("{:#?}", html_to_ir::convert_file(contents)),
-        _ => panic!("Filetype not supported."),
+        "html" | "htm" | "xhtml" => Ok(html_to_ir::convert_file_with(contents, pipeline)),
+        "json" => ir_json::from_json(contents),
+        _ => Err(format!("Filetype {} not supported.", file_type)),
     }
 }
 
-pub fn convert_file(debug: bool, file_type: &str, contents: &str) -> String {
-    if debug {
-        return convert_file_debug(file_type, contents);
+/// The options of the outputs that make a note from every chunk of the
+/// document, and of the clozes.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RenderOptions {
+    /// The most sentences in a note.
+    max_sentences: Option<usize>,
+    cloze: Option<cloze::Options>,
+    /// The deck, or category, the notes go in.
+    deck: Option<String>,
+    /// What identifies the document in the ids of its notes, as made by
+    /// `chunk::source`.
+    source: Option<String>,
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
     }
 
-    match file_type {
-        "html" | "xhtml" => {
-            let doc = html_to_ir::convert_file(contents);
-            ir_to_anki::convert(doc)
+    pub fn set_max_sentences(&mut self, max: usize) -> &mut Self {
+        self.max_sentences = Some(max);
+        self
+    }
+
+    pub fn set_cloze(&mut self, cloze: cloze::Options) -> &mut Self {
+        self.cloze = Some(cloze);
+        self
+    }
+
+    pub fn set_deck(&mut self, deck: &str) -> &mut Self {
+        self.deck = Some(deck.to_string());
+        self
+    }
+
+    pub fn set_source(&mut self, source: &str) -> &mut Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn max_sentences(&self) -> Option<usize> {
+        self.max_sentences
+    }
+
+    pub fn cloze(&self) -> Option<&cloze::Options> {
+        self.cloze.as_ref()
+    }
+
+    pub fn deck(&self) -> Option<&str> {
+        self.deck.as_deref()
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+}
+
+pub fn convert_file(output: Output,
+                    file_type: &str,
+                    contents: &str,
+                    options: &RenderOptions)
+                    -> Result<String, String> {
+    let pipeline = html_to_ir::pass::Pipeline::default();
+
+    convert_ir(file_type, contents, &pipeline).map(|doc| render_with(output, doc, options))
+}
+
+pub fn render(output: Output, doc: ir::Document) -> String {
+    render_with(output, doc, &RenderOptions::new())
+}
+
+/// Splits the document into the chunks that become notes, and makes the
+/// clozes of every note on its own.
+pub fn chunks(doc: ir::Document, options: &RenderOptions) -> Vec<chunk::Chunk> {
+    let chunks = chunk::chunk_with(doc, options.max_sentences);
+
+    match options.cloze {
+        Some(ref cloze) => cloze::cloze_chunks(chunks, cloze),
+        None => chunks,
+    }
+}
+
+/// Renders the document. Clozes are made in every note for the outputs that
+/// make a note from every chunk, or else in the whole document. Without a
+/// source, the ids of the notes are made from the URL of the document alone.
+pub fn render_with(output: Output, doc: ir::Document, options: &RenderOptions) -> String {
+    let source = || {
+        options.source()
+            .map(|source| source.to_string())
+            .unwrap_or_else(|| chunk::source(&doc, Path::new("")))
+    };
+
+    // The outputs without notes take the whole document as a single note.
+    let clozed = |doc| match options.cloze {
+        Some(ref cloze) => cloze::cloze(doc, cloze),
+        None => doc,
+    };
+
+    match output {
+        Output::AnkiNotes => {
+            let source = source();
+            let notes = ir_to_anki_notes::notes(chunks(doc, options), &source);
+            ir_to_anki_notes::convert(&notes, options.deck())
+        }
+        Output::SuperMemo => ir_to_supermemo::convert_chunks(chunks(doc, options)),
+        Output::Mnemosyne => {
+            let source = source();
+            ir_to_mnemosyne::convert_chunks(chunks(doc, options), &source, options.deck())
         }
-        _ => panic!("Filetype not supported."),
+        Output::Anki => ir_to_anki::convert(clozed(doc)),
+        Output::Markdown => ir_to_markdown::convert(clozed(doc)),
+        Output::Json => ir_json::to_json(&clozed(doc)),
+        Output::Trace => ir_to_trace::convert(clozed(doc)),
+        Output::Debug => format!("{:#?}", clozed(doc)),
     }
 }
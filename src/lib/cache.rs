@@ -0,0 +1,142 @@
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The version of the cache file. A cache of another version is ignored, so
+/// every note is treated as new.
+const VERSION: u32 = 1;
+
+/// What to do with a note compared to the previous conversion.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    New,
+    Updated,
+    Unchanged,
+}
+
+/// How many notes of a source were new, updated and unchanged, and how many
+/// of the previous notes are gone.
+#[derive(Debug, PartialEq, Default)]
+pub struct Report {
+    pub new: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// The notes of previous conversions, as the hash of the content of every note
+/// id of every source.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    sources: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Cache {
+            version: VERSION,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the cache, which is empty if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Cache::new()),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        };
+
+        let cache = serde_json::from_str::<Cache>(&contents)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        if cache.version != VERSION {
+            warn!("{}: Ignoring a cache of version {}", path.display(), cache.version);
+            return Ok(Cache::new());
+        }
+
+        Ok(cache)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).expect("The cache is always valid JSON");
+
+        fs::write(path, contents).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn status(&self, source: &str, id: &str, hash: &str) -> Status {
+        match self.sources.get(source).and_then(|notes| notes.get(id)) {
+            None => Status::New,
+            Some(previous) if previous != hash => Status::Updated,
+            Some(_) => Status::Unchanged,
+        }
+    }
+
+    /// Replaces the notes of the source, given as their ids and hashes, and
+    /// reports how they changed.
+    pub fn update(&mut self, source: &str, notes: &[(String, String)]) -> Report {
+        let mut report = Report::default();
+        for (id, hash) in notes {
+            match self.status(source, id, hash) {
+                Status::New => report.new += 1,
+                Status::Updated => report.updated += 1,
+                Status::Unchanged => report.unchanged += 1,
+            }
+        }
+
+        let notes = notes.iter().cloned().collect::<BTreeMap<_, _>>();
+        if let Some(previous) = self.sources.get(source) {
+            report.removed = previous.keys().filter(|id| !notes.contains_key(*id)).count();
+        }
+        self.sources.insert(source.to_string(), notes);
+
+        report
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(notes: &[(&str, &str)]) -> Vec<(String, String)> {
+        notes.iter().map(|&(id, hash)| (id.to_string(), hash.to_string())).collect()
+    }
+
+    #[test]
+    fn update() {
+        let mut cache = Cache::new();
+        let report = cache.update("a.html", &notes(&[("1", "x"), ("2", "y")]));
+        assert_eq!(report, Report { new: 2, ..Report::default() });
+
+        assert_eq!(cache.status("a.html", "1", "x"), Status::Unchanged);
+        assert_eq!(cache.status("a.html", "1", "z"), Status::Updated);
+        assert_eq!(cache.status("b.html", "1", "x"), Status::New);
+
+        let report = cache.update("a.html", &notes(&[("1", "z"), ("3", "w")]));
+        assert_eq!(report,
+                   Report {
+                       new: 1,
+                       updated: 1,
+                       unchanged: 0,
+                       removed: 1,
+                   });
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut cache = Cache::new();
+        cache.update("a.html", &notes(&[("1", "x")]));
+
+        let json = serde_json::to_string(&cache).unwrap();
+        assert_eq!(serde_json::from_str::<Cache>(&json).unwrap(), cache);
+    }
+}
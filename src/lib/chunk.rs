@@ -0,0 +1,388 @@
+use std::fs;
+use std::path::Path;
+
+use group_lines::sentence_starts;
+use hash::hash;
+use ir::{Document, Footnote, FootnoteRef, IR, Section, Text, TextBlock, Visitor};
+
+/// A part of a document that becomes a single note, along with the headings
+/// it is nested under.
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    headings: Vec<String>,
+    document: Document,
+}
+
+impl Chunk {
+    pub fn new(headings: Vec<String>) -> Self {
+        Chunk {
+            headings,
+            document: Document::new(),
+        }
+    }
+
+    pub fn add(self, item: IR) -> Self {
+        Chunk {
+            headings: self.headings,
+            document: self.document.add(item),
+        }
+    }
+
+    pub fn headings(&self) -> &[String] {
+        &self.headings
+    }
+
+    /// The heading path of the chunk, e.g. "Chapter 1 > Section 2".
+    pub fn title(&self) -> Option<String> {
+        if self.headings.is_empty() {
+            None
+        } else {
+            Some(self.headings.join(" > "))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.document.is_empty()
+    }
+
+    pub fn document(self) -> Document {
+        self.document
+    }
+
+    /// The chunk under the same headings with the function applied to its
+    /// content.
+    pub fn map<F: FnOnce(Document) -> Document>(self, f: F) -> Self {
+        Chunk {
+            headings: self.headings,
+            document: f(self.document),
+        }
+    }
+}
+
+/// The ids of the footnotes referenced in the document, in order of their
+/// first reference. References inside the footnotes themselves are ignored.
+struct FootnoteRefs(Vec<String>);
+
+impl Visitor for FootnoteRefs {
+    fn visit_footnote(&mut self, _footnote: &Footnote) {}
+
+    fn visit_footnote_ref(&mut self, reference: &FootnoteRef) {
+        if !self.0.iter().any(|id| id == reference.id()) {
+            self.0.push(reference.id().to_string());
+        }
+    }
+}
+
+fn footnote_refs(document: &Document) -> Vec<String> {
+    let mut refs = FootnoteRefs(vec![]);
+    refs.visit_document(document);
+
+    refs.0
+}
+
+/// The number of sentences in the paragraph. Only its plain text is read, so
+/// sentences ending inside inline elements are not counted.
+fn sentence_count(block: &TextBlock) -> usize {
+    if block.is_blank() {
+        return 0;
+    }
+
+    let starts = block.iter()
+        .map(|text| match *text {
+            Text::Text(ref x) => sentence_starts(x).into_iter().filter(|&x| x > 0).count(),
+            _ => 0,
+        })
+        .sum::<usize>();
+
+    starts + 1
+}
+
+/// Splits the paragraph into paragraphs of at most `max` sentences. It is only
+/// split in plain text, so inline elements stay whole.
+fn split_sentences(block: TextBlock, max: usize) -> Vec<TextBlock> {
+    let mut pieces = vec![];
+    let mut piece = TextBlock::new();
+    let mut count = 1;
+
+    for text in block {
+        let x = match text {
+            Text::Text(x) => x,
+            text => {
+                piece.add(text);
+                continue;
+            }
+        };
+
+        let mut start = 0;
+        for end in sentence_starts(&x).into_iter().filter(|&x| x > 0) {
+            if count < max {
+                count += 1;
+                continue;
+            }
+
+            piece.add(Text::text(x[start..end].trim_end()));
+            pieces.push(piece.build());
+            piece = TextBlock::new();
+            count = 1;
+            start = end;
+        }
+        if start < x.len() {
+            piece.add(Text::text(&x[start..]));
+        }
+    }
+    pieces.push(piece);
+
+    pieces.into_iter().filter(|piece| !piece.is_blank()).collect()
+}
+
+/// Splits the document at every heading. Each chunk keeps the path of headings
+/// above it, and content before the first heading becomes a chunk of its own.
+/// Chunks without any content are dropped.
+///
+/// Footnotes are moved to the end of every chunk that references them, and
+/// every chunk keeps the metadata of the document.
+pub fn chunk(document: Document) -> Vec<Chunk> {
+    chunk_with(document, None)
+}
+
+/// Chunks the document, also starting a new chunk under the same headings
+/// whenever a chunk would have more than `max_sentences` sentences. Longer
+/// paragraphs are split over several chunks.
+pub fn chunk_with(document: Document, max_sentences: Option<usize>) -> Vec<Chunk> {
+    let metadata = document.metadata().clone();
+    let tree = Section::tree(document);
+    let mut chunks = vec![];
+    let mut notes: Vec<Footnote> = vec![];
+
+    for (path, section) in tree.iter() {
+        let headings = path.iter().map(|x| x.to_string()).collect();
+        chunks.push(Chunk::new(headings));
+        let mut sentences = 0;
+
+        for child in section.children().iter().cloned() {
+            match child {
+                IR::Footnote(note) => notes.push(note),
+                IR::Par(block) => {
+                    let max = match max_sentences {
+                        Some(max) => max.max(1),
+                        None => {
+                            let chunk = chunks.pop().expect("There is always a chunk");
+                            chunks.push(chunk.add(IR::Par(block)));
+                            continue;
+                        }
+                    };
+
+                    for piece in split_sentences(block, max) {
+                        let count = sentence_count(&piece);
+                        let mut chunk = chunks.pop().expect("There is always a chunk");
+                        if sentences + count > max && !chunk.is_empty() {
+                            let headings = chunk.headings.clone();
+                            chunks.push(chunk);
+                            chunk = Chunk::new(headings);
+                            sentences = 0;
+                        }
+
+                        sentences += count;
+                        chunks.push(chunk.add(IR::Par(piece)));
+                    }
+                }
+                child => {
+                    let chunk = chunks.pop().expect("There is always a chunk");
+                    chunks.push(chunk.add(child));
+                }
+            }
+        }
+    }
+
+    chunks.into_iter()
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| {
+            footnote_refs(&chunk.document)
+                .iter()
+                .filter_map(|id| notes.iter().find(|note| note.id() == id))
+                .fold(chunk, |chunk, note| chunk.add(IR::from(note.clone())))
+        })
+        .map(|chunk| {
+            Chunk {
+                headings: chunk.headings,
+                document: chunk.document.set_metadata(metadata.clone()),
+            }
+        })
+        .collect()
+}
+
+/// What identifies the document in the ids of its chunks: its canonical URL,
+/// or else the path it was read from, made absolute so that it is the same
+/// from any directory.
+pub fn source(document: &Document, input: &Path) -> String {
+    match document.metadata().url() {
+        Some(url) => url.to_string(),
+        None => {
+            fs::canonicalize(input)
+                .unwrap_or_else(|_| input.to_path_buf())
+                .display()
+                .to_string()
+        }
+    }
+}
+
+/// An id for every chunk that stays the same across runs while the source and
+/// the headings above the chunk do, however its content changes. Chunks under
+/// the same headings are told apart by their order.
+pub fn ids(source: &str, chunks: &[Chunk]) -> Vec<String> {
+    let mut seen: Vec<&[String]> = vec![];
+
+    chunks.iter()
+        .map(|chunk| {
+            let occurrence = seen.iter().filter(|x| **x == chunk.headings()).count();
+            seen.push(chunk.headings());
+
+            let key = format!("{}\u{1f}{}\u{1f}{}",
+                              source,
+                              chunk.headings().join("\u{1f}"),
+                              occurrence);
+            format!("{:016x}", hash(key.as_bytes()))
+        })
+        .collect()
+}
+
+/// The start of the first line of some rendered content, for chunks that have
+/// no heading to use as a title.
+pub fn preview(content: &str) -> String {
+    let line = content.split("<br>")
+        .flat_map(|line| line.lines())
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+
+    if line.chars().count() > 60 {
+        format!("{}...", line.chars().take(60).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::Metadata;
+
+    fn par(text: &str) -> IR {
+        IR::from(TextBlock::from(text))
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(chunk(Document::new()), vec![]);
+    }
+
+    #[test]
+    fn no_headings() {
+        let doc = Document::new()
+            .add(par("a"))
+            .add(par("b"));
+        let result = vec![Chunk::new(vec![])
+                              .add(par("a"))
+                              .add(par("b"))];
+        assert_eq!(chunk(doc), result);
+    }
+
+    #[test]
+    fn nested_headings() {
+        let doc = Document::new()
+            .add(par("intro"))
+            .add(IR::header(1, "a"))
+            .add(IR::header(2, "b"))
+            .add(par("b"))
+            .add(IR::header(3, "c"))
+            .add(par("c"))
+            .add(IR::header(2, "d"))
+            .add(par("d"));
+        let result = vec![Chunk::new(vec![]).add(par("intro")),
+                          Chunk::new(vec!["a".to_string(), "b".to_string()]).add(par("b")),
+                          Chunk::new(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+                              .add(par("c")),
+                          Chunk::new(vec!["a".to_string(), "d".to_string()]).add(par("d"))];
+        assert_eq!(chunk(doc), result);
+    }
+
+    #[test]
+    fn footnotes() {
+        let note = Footnote::new("n1", "1", TextBlock::from("note"));
+        let reference = IR::from(TextBlock::new()
+            .add(Text::text("b"))
+            .add(Text::footnote_ref("n1", "1"))
+            .build());
+        let doc = Document::new()
+            .add(IR::header(1, "a"))
+            .add(par("a"))
+            .add(IR::header(1, "b"))
+            .add(reference.clone())
+            .add(IR::from(note.clone()));
+        let result = vec![Chunk::new(vec!["a".to_string()]).add(par("a")),
+                          Chunk::new(vec!["b".to_string()])
+                              .add(reference)
+                              .add(IR::from(note))];
+        assert_eq!(chunk(doc), result);
+    }
+
+    #[test]
+    fn max_sentences() {
+        let doc = Document::new()
+            .add(IR::header(1, "a"))
+            .add(par("One. Two. Three."))
+            .add(par("Four."))
+            .add(IR::from(TextBlock::new()
+                .add(Text::strong("Five. Six."))
+                .add(Text::text(" Seven. Eight. Nine."))
+                .build()));
+        let headings = vec!["a".to_string()];
+        let result = vec![Chunk::new(headings.clone()).add(par("One. Two.")),
+                          Chunk::new(headings.clone()).add(par("Three.")).add(par("Four.")),
+                          Chunk::new(headings.clone()).add(IR::from(TextBlock::new()
+                              .add(Text::strong("Five. Six."))
+                              .add(Text::text(" Seven. Eight."))
+                              .build())),
+                          Chunk::new(headings).add(par("Nine."))];
+        assert_eq!(chunk_with(doc, Some(2)), result);
+    }
+
+    #[test]
+    fn title() {
+        let chunk = Chunk::new(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(chunk.title(), Some("a > b".to_string()));
+        assert_eq!(Chunk::new(vec![]).title(), None);
+    }
+
+    #[test]
+    fn sources() {
+        let doc = Document::new().set_metadata(Metadata::new().set_url("https://a.org").build());
+        assert_eq!(source(&doc, Path::new("a.html")), "https://a.org");
+
+        let dir = ::std::env::current_dir().unwrap().canonicalize().unwrap();
+        assert_eq!(source(&Document::new(), Path::new(".")), dir.display().to_string());
+    }
+
+    #[test]
+    fn stable_ids() {
+        let chunks = vec![Chunk::new(vec![]).add(par("a")),
+                          Chunk::new(vec!["a".to_string()]).add(par("b")),
+                          Chunk::new(vec!["a".to_string()]).add(par("c"))];
+        let changed = vec![Chunk::new(vec![]).add(par("x")),
+                           Chunk::new(vec!["a".to_string()]).add(par("y")),
+                           Chunk::new(vec!["a".to_string()]).add(par("z"))];
+
+        let result = ids("a.html", &chunks);
+        assert_eq!(result, ids("a.html", &changed));
+        assert_ne!(result, ids("b.html", &chunks));
+        assert_ne!(result[1], result[2]);
+    }
+
+    #[test]
+    fn preview_first_line() {
+        assert_eq!(preview("\n  abc  \ndef"), "abc".to_string());
+        assert_eq!(preview("<br>abc<br>def"), "abc".to_string());
+        let long = "a".repeat(70);
+        assert_eq!(preview(&long), format!("{}...", "a".repeat(60)));
+    }
+}
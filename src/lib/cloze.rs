@@ -0,0 +1,250 @@
+use chunk::Chunk;
+use ir::*;
+use ir::visit;
+
+/// The inline elements that can be turned into a cloze.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ClozeKind {
+    Strong,
+    Emphasis,
+    Definition,
+    Code,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Numbering {
+    /// Every cloze is `c1`, so the note makes a single card.
+    Single,
+    /// Clozes are numbered `c1`, `c2`, ..., making one card per cloze.
+    Numbered,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+    numbering: Numbering,
+    kinds: Vec<ClozeKind>,
+    max: Option<usize>,
+}
+
+impl Options {
+    pub fn new(numbering: Numbering) -> Self {
+        Options {
+            numbering,
+            kinds: vec![],
+            max: None,
+        }
+    }
+
+    pub fn add(&mut self, kind: ClozeKind) -> &mut Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+
+    pub fn set_max(&mut self, max: usize) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+}
+
+struct Counter<'a> {
+    options: &'a Options,
+    count: usize,
+}
+
+impl<'a> Counter<'a> {
+    fn qualifies(&self, text: &Text) -> bool {
+        let kind = match *text {
+            Text::Strong(_) => ClozeKind::Strong,
+            Text::Emphasis(_) => ClozeKind::Emphasis,
+            Text::Definition(_) => ClozeKind::Definition,
+            Text::Code(_) => ClozeKind::Code,
+            _ => return false,
+        };
+
+        self.options.kinds.contains(&kind) && !text.is_blank()
+    }
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(max) = self.options.max {
+            if self.count >= max {
+                return None;
+            }
+        }
+        self.count += 1;
+
+        match self.options.numbering {
+            Numbering::Single => Some(1),
+            Numbering::Numbered => Some(self.count),
+        }
+    }
+}
+
+impl<'a> Fold for Counter<'a> {
+    fn fold_text(&mut self, text: Text) -> Text {
+        if self.qualifies(&text) {
+            if let Some(number) = self.next() {
+                return Text::Cloze(number, TextBlock::from(text));
+            }
+        }
+
+        match text {
+            Text::Cloze(..) => text,
+            x => visit::fold_text(self, x),
+        }
+    }
+}
+
+/// Wraps the qualifying inline elements of the document in clozes, including
+/// the ones nested in other inline elements and in footnotes. The document is
+/// treated as a single note, so numbering and the maximum apply across the
+/// whole of it.
+pub fn cloze(document: Document, options: &Options) -> Document {
+    let mut counter = Counter {
+        options,
+        count: 0,
+    };
+
+    counter.fold_document(document)
+}
+
+/// Clozes every chunk as a note of its own, so the numbering starts over and
+/// the maximum applies in each of them.
+pub fn cloze_chunks(chunks: Vec<Chunk>, options: &Options) -> Vec<Chunk> {
+    chunks.into_iter()
+        .map(|chunk| chunk.map(|document| cloze(document, options)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn par() -> IR {
+        IR::from(TextBlock::new()
+            .add(Text::strong("a"))
+            .add(Text::text(" is "))
+            .add(Text::code("b"))
+            .add(Text::text(" and "))
+            .add(Text::emphasis("c"))
+            .build())
+    }
+
+    #[test]
+    fn no_kinds() {
+        let options = Options::new(Numbering::Numbered);
+        let doc = Document::new().add(par());
+        let result = Document::new().add(par());
+        assert_eq!(cloze(doc, &options), result);
+    }
+
+    #[test]
+    fn numbered() {
+        let options = Options::new(Numbering::Numbered)
+            .add(ClozeKind::Strong)
+            .add(ClozeKind::Code)
+            .build();
+        let doc = Document::new().add(par());
+        let result = Document::new().add(IR::from(TextBlock::new()
+            .add(Text::Cloze(1, TextBlock::from(Text::strong("a"))))
+            .add(Text::text(" is "))
+            .add(Text::Cloze(2, TextBlock::from(Text::code("b"))))
+            .add(Text::text(" and "))
+            .add(Text::emphasis("c"))
+            .build()));
+        assert_eq!(cloze(doc, &options), result);
+    }
+
+    #[test]
+    fn single() {
+        let options = Options::new(Numbering::Single)
+            .add(ClozeKind::Strong)
+            .add(ClozeKind::Emphasis)
+            .build();
+        let doc = Document::new().add(par());
+        let result = Document::new().add(IR::from(TextBlock::new()
+            .add(Text::Cloze(1, TextBlock::from(Text::strong("a"))))
+            .add(Text::text(" is "))
+            .add(Text::code("b"))
+            .add(Text::text(" and "))
+            .add(Text::Cloze(1, TextBlock::from(Text::emphasis("c"))))
+            .build()));
+        assert_eq!(cloze(doc, &options), result);
+    }
+
+    #[test]
+    fn max() {
+        let options = Options::new(Numbering::Numbered)
+            .add(ClozeKind::Strong)
+            .add(ClozeKind::Definition)
+            .set_max(1)
+            .build();
+        let item = ListItem::item(TextBlock::from(Text::definition("a")));
+        let doc = Document::new()
+            .add(par())
+            .add(IR::from(List::new(ListType::Unordered).add(item.clone()).build()));
+        let result = Document::new()
+            .add(IR::from(TextBlock::new()
+                .add(Text::Cloze(1, TextBlock::from(Text::strong("a"))))
+                .add(Text::text(" is "))
+                .add(Text::code("b"))
+                .add(Text::text(" and "))
+                .add(Text::emphasis("c"))
+                .build()))
+            .add(IR::from(List::new(ListType::Unordered).add(item).build()));
+        assert_eq!(cloze(doc, &options), result);
+    }
+
+    #[test]
+    fn nested() {
+        let options = Options::new(Numbering::Numbered)
+            .add(ClozeKind::Emphasis)
+            .build();
+        let strong = |text| Text::Strong(TextBlock::from(text));
+        let note = |text| Footnote::new("n1", "1", TextBlock::from(text));
+        let doc = Document::new()
+            .add(IR::from(TextBlock::from(strong(Text::emphasis("a")))))
+            .add(IR::from(note(Text::emphasis("b"))));
+        let clozed = |number, text| Text::Cloze(number, TextBlock::from(Text::emphasis(text)));
+        let result = Document::new()
+            .add(IR::from(TextBlock::from(strong(clozed(1, "a")))))
+            .add(IR::from(note(clozed(2, "b"))));
+        assert_eq!(cloze(doc, &options), result);
+    }
+
+    #[test]
+    fn per_chunk() {
+        let options = Options::new(Numbering::Numbered)
+            .add(ClozeKind::Strong)
+            .set_max(1)
+            .build();
+        let par = |text| IR::from(TextBlock::new()
+            .add(Text::strong(text))
+            .add(Text::strong(text))
+            .build());
+        let chunks = vec![Chunk::new(vec![]).add(par("a")),
+                          Chunk::new(vec!["b".to_string()]).add(par("b"))];
+        let clozed = |text| IR::from(TextBlock::new()
+            .add(Text::Cloze(1, TextBlock::from(Text::strong(text))))
+            .add(Text::strong(text))
+            .build());
+        let result = vec![Chunk::new(vec![]).add(clozed("a")),
+                          Chunk::new(vec!["b".to_string()]).add(clozed("b"))];
+        assert_eq!(cloze_chunks(chunks, &options), result);
+    }
+
+    #[test]
+    fn blank() {
+        let options = Options::new(Numbering::Numbered)
+            .add(ClozeKind::Strong)
+            .build();
+        let doc = Document::new().add(IR::from(TextBlock::from(Text::strong(" "))));
+        let result = Document::new().add(IR::from(TextBlock::from(Text::strong(" "))));
+        assert_eq!(cloze(doc, &options), result);
+    }
+}
@@ -0,0 +1,490 @@
+extern crate glob;
+extern crate toml;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use self::glob::Pattern;
+
+use cloze;
+use html_to_ir;
+use html_to_ir::pass::{DropMatching, KeepMatching, Pipeline, NORMALIZE};
+use html_to_ir::selector::Selector;
+use {Output, RenderOptions};
+
+/// The name of the project-local config file, looked for in the current
+/// directory and its parents.
+pub const PROJECT_FILE: &str = ".anki_convert.toml";
+
+/// The conversion options for a kind of document. Options that are not set
+/// fall back to the options of the profiles below it, so a profile only has to
+/// give what differs.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    name: Option<String>,
+    /// Domains the canonical URL of the document can be on, including their
+    /// subdomains.
+    domains: Vec<String>,
+    /// Globs the path of the document can match.
+    paths: Vec<String>,
+    from: Option<String>,
+    to: Option<String>,
+    encoding: Option<String>,
+    keep: Vec<String>,
+    drop: Vec<String>,
+    cloze: Vec<String>,
+    cloze_numbering: Option<String>,
+    cloze_max: Option<usize>,
+    /// The most sentences in a note, for outputs with a note per chunk, or in
+    /// a paragraph for `group_lines`.
+    max_sentences: Option<usize>,
+    /// The normalisations to apply to the text: `nfc`, `quotes` and `dashes`.
+    normalize: Vec<String>,
+    media: Option<PathBuf>,
+    /// The deck the notes are imported into, for the `anki-notes` output, or
+    /// the category of the Mnemosyne items.
+    deck: Option<String>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Profile::default()
+    }
+
+    pub fn set_from(&mut self, from: &str) -> &mut Self {
+        self.from = Some(from.to_string());
+        self
+    }
+
+    pub fn set_to(&mut self, to: &str) -> &mut Self {
+        self.to = Some(to.to_string());
+        self
+    }
+
+    pub fn set_encoding(&mut self, encoding: &str) -> &mut Self {
+        self.encoding = Some(encoding.to_string());
+        self
+    }
+
+    pub fn add_keep(&mut self, selector: &str) -> &mut Self {
+        self.keep.push(selector.to_string());
+        self
+    }
+
+    pub fn add_drop(&mut self, selector: &str) -> &mut Self {
+        self.drop.push(selector.to_string());
+        self
+    }
+
+    pub fn add_cloze(&mut self, kind: &str) -> &mut Self {
+        self.cloze.push(kind.to_string());
+        self
+    }
+
+    pub fn set_cloze_numbering(&mut self, numbering: &str) -> &mut Self {
+        self.cloze_numbering = Some(numbering.to_string());
+        self
+    }
+
+    pub fn set_cloze_max(&mut self, max: usize) -> &mut Self {
+        self.cloze_max = Some(max);
+        self
+    }
+
+    pub fn set_max_sentences(&mut self, max: usize) -> &mut Self {
+        self.max_sentences = Some(max);
+        self
+    }
+
+    pub fn add_normalize(&mut self, kind: &str) -> &mut Self {
+        self.normalize.push(kind.to_string());
+        self
+    }
+
+    pub fn set_media(&mut self, media: &Path) -> &mut Self {
+        self.media = Some(media.to_path_buf());
+        self
+    }
+
+    pub fn set_deck(&mut self, deck: &str) -> &mut Self {
+        self.deck = Some(deck.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    pub fn encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
+    pub fn max_sentences(&self) -> Option<usize> {
+        self.max_sentences
+    }
+
+    pub fn media(&self) -> Option<&Path> {
+        self.media.as_deref()
+    }
+
+    pub fn deck(&self) -> Option<&str> {
+        self.deck.as_deref()
+    }
+
+    /// This profile with the options set in the other one replacing its own.
+    pub fn merge(&self, other: &Profile) -> Profile {
+        fn list(a: &[String], b: &[String]) -> Vec<String> {
+            if b.is_empty() { a.to_vec() } else { b.to_vec() }
+        }
+
+        Profile {
+            name: other.name.clone().or_else(|| self.name.clone()),
+            domains: list(&self.domains, &other.domains),
+            paths: list(&self.paths, &other.paths),
+            from: other.from.clone().or_else(|| self.from.clone()),
+            to: other.to.clone().or_else(|| self.to.clone()),
+            encoding: other.encoding.clone().or_else(|| self.encoding.clone()),
+            keep: list(&self.keep, &other.keep),
+            drop: list(&self.drop, &other.drop),
+            cloze: list(&self.cloze, &other.cloze),
+            cloze_numbering: other.cloze_numbering.clone().or_else(|| self.cloze_numbering.clone()),
+            cloze_max: other.cloze_max.or(self.cloze_max),
+            max_sentences: other.max_sentences.or(self.max_sentences),
+            normalize: list(&self.normalize, &other.normalize),
+            media: other.media.clone().or_else(|| self.media.clone()),
+            deck: other.deck.clone().or_else(|| self.deck.clone()),
+        }
+    }
+
+    /// Whether the profile is for the document at the path, or with the
+    /// canonical URL.
+    pub fn matches(&self, path: &Path, url: Option<&str>) -> bool {
+        let host = url.and_then(host);
+        let domain = host.is_some_and(|host| {
+            self.domains.iter().any(|domain| {
+                let domain = domain.trim_start_matches('.').to_lowercase();
+                host == domain || host.ends_with(&format!(".{}", domain))
+            })
+        });
+
+        domain ||
+        self.paths
+            .iter()
+            .filter_map(|glob| Pattern::new(glob).ok())
+            .any(|pattern| pattern.matches_path(path))
+    }
+
+    pub fn output(&self) -> Result<Output, String> {
+        match self.to.as_deref() {
+            None | Some("anki") => Ok(Output::Anki),
+            Some("anki-notes") => Ok(Output::AnkiNotes),
+            Some("markdown") => Ok(Output::Markdown),
+            Some("supermemo") => Ok(Output::SuperMemo),
+            Some("mnemosyne") => Ok(Output::Mnemosyne),
+            Some("json") => Ok(Output::Json),
+            Some("trace") => Ok(Output::Trace),
+            Some(x) => Err(format!("Unknown output format {}", x)),
+        }
+    }
+
+    pub fn cloze_options(&self) -> Result<Option<cloze::Options>, String> {
+        if self.cloze.is_empty() {
+            return Ok(None);
+        }
+
+        let numbering = match self.cloze_numbering.as_deref() {
+            None | Some("numbered") => cloze::Numbering::Numbered,
+            Some("single") => cloze::Numbering::Single,
+            Some(x) => return Err(format!("Unknown cloze numbering {}", x)),
+        };
+        let mut options = cloze::Options::new(numbering);
+
+        for kind in &self.cloze {
+            let kind = match kind.as_str() {
+                "strong" => cloze::ClozeKind::Strong,
+                "emphasis" => cloze::ClozeKind::Emphasis,
+                "definition" => cloze::ClozeKind::Definition,
+                "code" => cloze::ClozeKind::Code,
+                x => return Err(format!("Unknown cloze kind {}", x)),
+            };
+            options.add(kind);
+        }
+
+        if let Some(max) = self.cloze_max {
+            options.set_max(max);
+        }
+
+        Ok(Some(options))
+    }
+
+    /// The options for rendering: the chunk size, clozes and deck.
+    pub fn render_options(&self) -> Result<RenderOptions, String> {
+        let mut options = RenderOptions::new();
+        if let Some(max) = self.max_sentences {
+            options.set_max_sentences(max);
+        }
+        if let Some(cloze) = self.cloze_options()? {
+            options.set_cloze(cloze);
+        }
+        if let Some(ref deck) = self.deck {
+            options.set_deck(deck);
+        }
+
+        Ok(options)
+    }
+
+    /// The passes to run, with the elements to keep and drop picked out before
+    /// any of the built-in passes and the text normalised after them.
+    pub fn pipeline(&self) -> Result<Pipeline, String> {
+        let mut pipeline = Pipeline::default();
+        if !self.keep.is_empty() {
+            let keep = Selector::parse(&self.keep.join(", "))?;
+            pipeline.insert_before("footnotes", KeepMatching::new(keep));
+        }
+        if !self.drop.is_empty() {
+            let drop = Selector::parse(&self.drop.join(", "))?;
+            pipeline.insert_before("footnotes", DropMatching::new(drop));
+        }
+        for kind in &self.normalize {
+            if !NORMALIZE.contains(&kind.as_str()) {
+                return Err(format!("Unknown normalization {}", kind));
+            }
+            pipeline.add_builtin(kind)?;
+        }
+
+        Ok(pipeline)
+    }
+}
+
+/// The host of a URL, in lower case and without any `www.`.
+fn host(url: &str) -> Option<String> {
+    let rest = &url[url.find("://")? + 3..];
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let host = &rest[..end];
+    let host = &host[host.rfind('@').map_or(0, |x| x + 1)..];
+    let host = host.split(':').next().unwrap_or("").to_lowercase();
+
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+/// The canonical URL of the document, as read into its metadata.
+pub fn canonical_url(contents: &str) -> Option<String> {
+    html_to_ir::metadata(contents).url().map(|url| url.to_string())
+}
+
+/// Profiles from the config files. Settings in `[default]` apply to every
+/// document, and each `[[profile]]` applies to the documents it matches.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    default: Profile,
+    profile: Vec<Profile>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let config = toml::from_str::<Config>(contents).map_err(|err| err.to_string())?;
+
+        for profile in config.profile.iter().chain(Some(&config.default)) {
+            profile.output()?;
+            profile.render_options()?;
+            profile.pipeline()?;
+            for glob in &profile.paths {
+                Pattern::new(glob).map_err(|err| format!("Invalid glob {}: {}", glob, err))?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+
+        Config::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// The user-level config file, followed by the closest project-local one.
+    pub fn locate() -> Vec<PathBuf> {
+        let user = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("anki_convert").join("config.toml"));
+
+        let project = env::current_dir().ok().and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(PROJECT_FILE))
+                .find(|path| path.is_file())
+        });
+
+        user.into_iter()
+            .filter(|path| path.is_file())
+            .chain(project)
+            .collect()
+    }
+
+    /// Loads the config files in order, with later files taking precedence.
+    pub fn load_all(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut config = Config::new();
+        for path in paths {
+            config = config.merge(Config::load(path)?);
+        }
+
+        Ok(config)
+    }
+
+    /// This config with the other one taking precedence. Its profiles are
+    /// matched first.
+    pub fn merge(self, other: Config) -> Config {
+        let mut profile = other.profile;
+        profile.extend(self.profile);
+
+        Config {
+            default: self.default.merge(&other.default),
+            profile,
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profile.iter().find(|profile| profile.name() == Some(name))
+    }
+
+    /// The options for a document, from the named profile or else the first
+    /// profile matching the document, over the defaults.
+    pub fn resolve(&self, name: Option<&str>, path: &Path, url: Option<&str>) -> Result<Profile, String> {
+        let profile = match name {
+            Some(name) => {
+                Some(self.profile(name).ok_or_else(|| format!("Unknown profile {}", name))?)
+            }
+            None => self.profile.iter().find(|profile| profile.matches(path, url)),
+        };
+
+        Ok(match profile {
+            Some(profile) => self.default.merge(profile),
+            None => self.default.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r##"
+        [default]
+        to = "markdown"
+        drop = [".ad-slot"]
+
+        [[profile]]
+        name = "wikipedia"
+        domains = ["wikipedia.org"]
+        keep = ["#mw-content-text"]
+        drop = [".navbox", ".reference"]
+        cloze = ["strong"]
+        max_sentences = 3
+        deck = "Biology"
+
+        [[profile]]
+        name = "notes"
+        paths = ["**/notes/*.html"]
+        to = "anki"
+        encoding = "windows-1252"
+    "##;
+
+    #[test]
+    fn domain() {
+        let config = Config::parse(CONFIG).unwrap();
+        let profile = config.resolve(None,
+                     Path::new("a.html"),
+                     Some("https://en.wikipedia.org/wiki/Cell"))
+            .unwrap();
+
+        assert_eq!(profile.name(), Some("wikipedia"));
+        assert_eq!(profile.output(), Ok(Output::Markdown));
+        assert_eq!(profile.drop, vec![".navbox", ".reference"]);
+        assert!(profile.cloze_options().unwrap().is_some());
+        let options = profile.render_options().unwrap();
+        assert_eq!(options.max_sentences(), Some(3));
+        assert_eq!(options.deck(), Some("Biology"));
+    }
+
+    #[test]
+    fn path() {
+        let config = Config::parse(CONFIG).unwrap();
+        let profile = config.resolve(None, Path::new("home/notes/a.html"), None).unwrap();
+
+        assert_eq!(profile.name(), Some("notes"));
+        assert_eq!(profile.output(), Ok(Output::Anki));
+        assert_eq!(profile.encoding(), Some("windows-1252"));
+        assert_eq!(profile.drop, vec![".ad-slot"]);
+    }
+
+    #[test]
+    fn fallback() {
+        let config = Config::parse(CONFIG).unwrap();
+        let profile = config.resolve(None, Path::new("a.html"), Some("https://wikipedia.com"))
+            .unwrap();
+
+        assert_eq!(profile.name(), None);
+        assert_eq!(profile.output(), Ok(Output::Markdown));
+        assert!(config.resolve(Some("nope"), Path::new("a.html"), None).is_err());
+    }
+
+    #[test]
+    fn command_line_overrides() {
+        let config = Config::parse(CONFIG).unwrap();
+        let cli = Profile::new().set_to("json").add_cloze("code").build();
+        let profile = config.resolve(Some("wikipedia"), Path::new("a.html"), None)
+            .unwrap()
+            .merge(&cli);
+
+        assert_eq!(profile.output(), Ok(Output::Json));
+        assert_eq!(profile.cloze, vec!["code"]);
+        assert_eq!(profile.keep, vec!["#mw-content-text"]);
+    }
+
+    #[test]
+    fn project_overrides_user() {
+        let user = Config::parse("[default]\nto = \"json\"\nencoding = \"utf-8\"").unwrap();
+        let project = Config::parse("[default]\nto = \"anki\"").unwrap();
+        let profile = user.merge(project).resolve(None, Path::new("a"), None).unwrap();
+
+        assert_eq!(profile.output(), Ok(Output::Anki));
+        assert_eq!(profile.encoding(), Some("utf-8"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Config::parse("[default]\nto = \"pdf\"").is_err());
+        assert!(Config::parse("[default]\nkeep = [\"a[\"]").is_err());
+        assert!(Config::parse("[default]\ncolour = \"red\"").is_err());
+        assert!(Config::parse("[default]\nnormalize = [\"ascii\"]").is_err());
+    }
+
+    #[test]
+    fn canonical() {
+        let html = "<html><head>\
+                    <link rel=\"stylesheet\" href=\"a.css\">\
+                    <link rel=\"canonical\" href=\"https://En.Wikipedia.org/wiki/Cell\">\
+                    </head><body></body></html>";
+        assert_eq!(canonical_url(html), Some("https://En.Wikipedia.org/wiki/Cell".to_string()));
+
+        let html = "<head><meta property=\"og:url\" content=\"https://example.com/\"></head>";
+        assert_eq!(canonical_url(html), Some("https://example.com/".to_string()));
+        assert_eq!(host("https://user@www.Example.com:80/a"), Some("example.com".to_string()));
+    }
+}
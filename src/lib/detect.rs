@@ -0,0 +1,105 @@
+use std::path::Path;
+
+/// The file type given by the extension of a file name, ignoring any query or
+/// fragment left over from a URL.
+pub fn file_type(name: &Path) -> Option<String> {
+    let name = name.file_name()?.to_str()?;
+    let name = match name.find(['?', '#']) {
+        Some(end) => &name[..end],
+        None => name,
+    };
+
+    let dot = name.rfind('.')?;
+    let extension = name[dot + 1..].to_lowercase();
+
+    if extension.is_empty() {
+        None
+    } else {
+        Some(extension)
+    }
+}
+
+/// The value of `name=` in the text of a tag, with or without quotes.
+pub fn attribute_value<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    loop {
+        let start = rest.find(name)?;
+        let before = rest[..start].chars().next_back();
+        rest = &rest[start + name.len()..];
+
+        if before.is_some_and(|x| x.is_alphanumeric() || x == '-') {
+            continue;
+        }
+        let value = match rest.trim_start().strip_prefix('=') {
+            Some(value) => value.trim_start(),
+            None => continue,
+        };
+
+        return match value.chars().next() {
+            Some(quote @ '"') | Some(quote @ '\'') => {
+                let value = &value[1..];
+                value.find(quote).map(|end| &value[..end])
+            }
+            _ => {
+                let end = value.find(|x: char| x.is_whitespace() || "\"'>;".contains(x))
+                    .unwrap_or(value.len());
+                Some(&value[..end])
+            }
+        };
+    }
+}
+
+/// Guesses the file type from the start of the contents.
+pub fn sniff(contents: &[u8]) -> Option<&'static str> {
+    if contents.starts_with(b"PK\x03\x04") {
+        return Some("zip");
+    }
+
+    let contents = contents.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(contents);
+    let start = contents.iter()
+        .position(|x| !x.is_ascii_whitespace())
+        .unwrap_or(contents.len());
+    let head = contents[start..]
+        .iter()
+        .take(1024)
+        .map(|x| x.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+
+    if head.starts_with(b"<?xml") {
+        let is_html = head.windows(5).any(|x| x == b"<html");
+        Some(if is_html { "xhtml" } else { "xml" })
+    } else if head.starts_with(b"<!doctype html") || head.starts_with(b"<html") ||
+              head.starts_with(b"<!--") || head.starts_with(b"<head") ||
+              head.starts_with(b"<body") {
+        Some("html")
+    } else if head.starts_with(b"{") {
+        Some("json")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(file_type(Path::new("a/page.HTM")), Some("htm".to_string()));
+        assert_eq!(file_type(Path::new("page.html?x=1")), Some("html".to_string()));
+        assert_eq!(file_type(Path::new("a.b/page")), None);
+        assert_eq!(file_type(Path::new("page.")), None);
+    }
+
+    #[test]
+    fn contents() {
+        assert_eq!(sniff(b"\xEF\xBB\xBF\n  <!DOCTYPE html><p>a</p>"), Some("html"));
+        assert_eq!(sniff(b"<html><body></body></html>"), Some("html"));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<html xmlns=\"x\">"),
+                   Some("xhtml"));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<container>"), Some("xml"));
+        assert_eq!(sniff(b"PK\x03\x04mimetype"), Some("zip"));
+        assert_eq!(sniff(b"{\"version\": 3}"), Some("json"));
+        assert_eq!(sniff(b"plain text"), None);
+    }
+}
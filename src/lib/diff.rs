@@ -0,0 +1,157 @@
+/// The largest table of line pairs to compare. Changes between longer runs of
+/// lines are shown as removing all the old lines and adding all the new ones.
+const MAX_TABLE: usize = 4_000_000;
+
+/// A line of a diff between an old and a new text.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Compares the texts line by line, keeping their longest common subsequence
+/// of lines.
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut result = old[..prefix].iter().map(|x| Line::Same(x)).collect::<Vec<_>>();
+
+    if (a.len() + 1) * (b.len() + 1) <= MAX_TABLE {
+        // The length of the common subsequence of a[i..] and b[j..].
+        let width = b.len() + 1;
+        let mut table = vec![0; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                table[i * width + j] = if a[i] == b[j] {
+                    table[(i + 1) * width + j + 1] + 1
+                } else {
+                    table[(i + 1) * width + j].max(table[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                result.push(Line::Same(a[i]));
+                i += 1;
+                j += 1;
+            } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+                result.push(Line::Removed(a[i]));
+                i += 1;
+            } else {
+                result.push(Line::Added(b[j]));
+                j += 1;
+            }
+        }
+        result.extend(a[i..].iter().map(|x| Line::Removed(x)));
+        result.extend(b[j..].iter().map(|x| Line::Added(x)));
+    } else {
+        result.extend(a.iter().map(|x| Line::Removed(x)));
+        result.extend(b.iter().map(|x| Line::Added(x)));
+    }
+
+    result.extend(old[old.len() - suffix..].iter().map(|x| Line::Same(x)));
+
+    result
+}
+
+/// The changed lines with `context` lines around them, in hunks headed by
+/// their position in both texts as in a unified diff. Equal texts have an
+/// empty diff.
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let lines = lines(old, new);
+
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (index, line) in lines.iter().enumerate() {
+        if let Line::Same(_) = *line {
+            continue;
+        }
+
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // The line numbers in the old and new text at each line of the diff.
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match *line {
+            Line::Same(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Line::Removed(_) => old_line += 1,
+            Line::Added(_) => new_line += 1,
+        }
+    }
+
+    let mut diff = String::new();
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|x| !matches!(**x, Line::Added(_))).count();
+        let new_count = hunk.iter().filter(|x| !matches!(**x, Line::Removed(_))).count();
+
+        // An empty side is numbered by the line before it, as in unified diffs.
+        let (old_line, new_line) = positions[start];
+        let old_line = if old_count == 0 { old_line - 1 } else { old_line };
+        let new_line = if new_count == 0 { new_line - 1 } else { new_line };
+
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", old_line, old_count, new_line, new_count));
+        for line in hunk {
+            let (prefix, text) = match *line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            diff.push(prefix);
+            diff.push_str(text);
+            diff.push('\n');
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_changed() {
+        assert_eq!(lines("a\nb\nc\nd", "a\nc\nx\nd"),
+                   vec![Line::Same("a"),
+                        Line::Removed("b"),
+                        Line::Same("c"),
+                        Line::Added("x"),
+                        Line::Same("d")]);
+    }
+
+    #[test]
+    fn unified_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10";
+
+        assert_eq!(unified(old, new, 1),
+                   "@@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n@@ -9,1 +9,2 @@\n 9\n+10\n");
+        assert_eq!(unified(old, old, 1), "");
+        assert_eq!(unified("a", "", 0), "@@ -1,1 +0,0 @@\n-a\n");
+    }
+}
@@ -0,0 +1,124 @@
+extern crate chardetng;
+extern crate encoding_rs;
+
+use self::chardetng::EncodingDetector;
+pub use self::encoding_rs::Encoding;
+use self::encoding_rs::UTF_8;
+
+use detect::attribute_value;
+
+/// How far into the document to look for a declared encoding, as browsers do.
+const PRESCAN_LENGTH: usize = 1024;
+
+/// The encoding with the given label, such as `utf-8` or `windows-1252`.
+pub fn for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// The encoding declared by the XML declaration or a `meta` tag.
+fn declared(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(PRESCAN_LENGTH)];
+    let head = String::from_utf8_lossy(head).to_lowercase();
+
+    if head.trim_start().starts_with("<?xml") {
+        let end = head.find("?>").unwrap_or(head.len());
+        if let Some(label) = attribute_value(&head[..end], "encoding") {
+            return for_label(label);
+        }
+    }
+
+    for (start, _) in head.match_indices("<meta") {
+        let tag = &head[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+
+        let encoding = attribute_value(tag, "charset").and_then(for_label).or_else(|| {
+            let content = attribute_value(tag, "content")?;
+            attribute_value(content, "charset").and_then(for_label)
+        });
+        // Pages saved as UTF-16 are found by their byte order mark, so a
+        // declaration of UTF-16 in ASCII text can only mean UTF-8.
+        if let Some(encoding) = encoding {
+            return Some(encoding.output_encoding());
+        }
+    }
+
+    None
+}
+
+/// Guesses the encoding of a document from its byte order mark, its declared
+/// encoding or, failing both, its contents.
+pub fn detect(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if let Some(encoding) = declared(bytes) {
+        return encoding;
+    }
+    if ::std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// Decodes a document into UTF-8, with the given encoding or the detected one.
+pub fn decode(bytes: &[u8], label: Option<&str>) -> Result<String, String> {
+    let encoding = match label {
+        Some(label) => for_label(label).ok_or_else(|| format!("Unknown encoding {}", label))?,
+        None => detect(bytes),
+    };
+
+    let (contents, malformed) = encoding.decode_with_bom_removal(bytes);
+    if malformed {
+        warn!("Some characters are not valid {} and were replaced.", encoding.name());
+    }
+
+    Ok(contents.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1252};
+
+    #[test]
+    fn bom() {
+        assert_eq!(detect(b"\xEF\xBB\xBF<p>a</p>"), UTF_8);
+        assert_eq!(detect(b"\xFF\xFE<\x00p\x00>\x00"), UTF_16LE);
+        assert_eq!(decode(b"\xEF\xBB\xBF<p>a</p>", None), Ok("<p>a</p>".to_string()));
+    }
+
+    #[test]
+    fn meta() {
+        assert_eq!(detect(b"<head><meta charset=\"windows-1252\"></head>"), WINDOWS_1252);
+        assert_eq!(detect(b"<head><meta charset=iso-8859-1></head>"), WINDOWS_1252);
+        assert_eq!(detect(b"<meta http-equiv=\"Content-Type\" \
+                           content=\"text/html; charset=Shift_JIS\">"),
+                   SHIFT_JIS);
+        assert_eq!(detect(b"<meta name=\"charset-info\" content=\"x\">"), UTF_8);
+        assert_eq!(detect(b"<meta charset=\"utf-16\">"), UTF_8);
+    }
+
+    #[test]
+    fn xml_declaration() {
+        assert_eq!(detect(b"<?xml version=\"1.0\" encoding='Shift_JIS'?><html/>"),
+                   SHIFT_JIS);
+    }
+
+    #[test]
+    fn heuristic() {
+        let bytes = b"<p>Caf\xE9 cr\xE8me, na\xEFve r\xE9sum\xE9, d\xE9j\xE0 vu.</p>";
+        assert_eq!(detect(bytes), WINDOWS_1252);
+        assert_eq!(decode(bytes, None),
+                   Ok("<p>Café crème, naïve résumé, déjà vu.</p>".to_string()));
+    }
+
+    #[test]
+    fn label() {
+        assert_eq!(decode(b"<meta charset=utf-8>\xE9", Some("latin1")),
+                   Ok("<meta charset=utf-8>é".to_string()));
+        assert!(decode(b"", Some("nonsense")).is_err());
+    }
+}
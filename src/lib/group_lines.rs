@@ -17,17 +17,183 @@ fn multi_to_single(lines: &str) -> String {
         .to_string()
 }
 
+/// Words that end with a full stop without ending the sentence, in lower case
+/// and without their last full stop.
+const ABBREVIATIONS: &[&str] = &["e.g", "i.e", "cf", "vs", "al", "approx", "ca", "dr", "mr",
+                                 "mrs", "ms", "prof", "st", "jr", "sr", "ed", "eds", "inc",
+                                 "ltd", "co"];
+
+/// Abbreviations that come before a number, as in "p. 5". They are only taken
+/// as one when a number follows, since words like "no" also end sentences.
+const NUMBERED: &[&str] = &["no", "vol", "p", "pp", "fig", "figs", "ch"];
+
+/// The start of the word that ends at the byte offset.
+fn word_start(text: &str, end: usize) -> usize {
+    text[..end]
+        .rfind(|c: char| c.is_whitespace() || c == '(' || c == '"')
+        .map(|x| x + 1)
+        .unwrap_or(0)
+}
+
+/// Whether the full stop at the byte offset is part of the word before it,
+/// as in an abbreviation or an initial. A single capital letter is only an
+/// initial after a capitalised word or at the start, as in "J. R. R. Tolkien",
+/// and not after a word in lower case, as in "a grade of A.".
+fn is_abbreviation(text: &str, stop: usize) -> bool {
+    let start = word_start(text, stop);
+    let word = text[start..stop].to_lowercase();
+    if ABBREVIATIONS.contains(&word.as_str()) {
+        return true;
+    }
+    if NUMBERED.contains(&word.as_str()) {
+        let next = text[stop + 1..].trim_start().chars().next();
+        return next.is_some_and(|c| c.is_ascii_digit());
+    }
+
+    let mut chars = text[start..stop].chars();
+    let initial = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase());
+    let before = text[..start].trim_end();
+    let previous = before[word_start(before, before.len())..].chars().next();
+
+    initial && previous.is_none_or(char::is_uppercase)
+}
+
+/// The byte offsets where every sentence after the first starts. A sentence
+/// ends with a full stop, question mark, exclamation mark or ellipsis, along
+/// with any closing quotes or brackets, and the next one starts after the
+/// whitespace that follows unless it starts in lower case. Full stops in
+/// decimals and abbreviations do not end a sentence.
+///
+/// Whitespace at the end of the text is taken as the start of another
+/// sentence, since the text may be followed by more.
+pub fn sentence_starts(text: &str) -> Vec<usize> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut starts = vec![];
+
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        i += 1;
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            continue;
+        }
+
+        // Runs of stops, such as an ellipsis or "?!", end a sentence once.
+        while i < chars.len() && matches!(chars[i].1, '.' | '!' | '?' | '…') {
+            i += 1;
+        }
+        let single_stop = c == '.' && chars[i - 1].0 == offset;
+        if single_stop && is_abbreviation(text, offset) {
+            continue;
+        }
+
+        while i < chars.len() && matches!(chars[i].1, '"' | '\'' | '”' | '’' | ')' | ']' | '»') {
+            i += 1;
+        }
+        if i < chars.len() && !chars[i].1.is_whitespace() {
+            // Decimals, or stops inside a word such as in a URL.
+            continue;
+        }
+        while i < chars.len() && chars[i].1.is_whitespace() {
+            i += 1;
+        }
+
+        match chars.get(i) {
+            Some(&(_, next)) if next.is_lowercase() => (),
+            Some(&(start, _)) => starts.push(start),
+            None if chars.last().is_some_and(|&(_, c)| c.is_whitespace()) => {
+                starts.push(text.len())
+            }
+            None => (),
+        }
+    }
+
+    starts
+}
+
+/// Splits the text into its sentences.
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut start = 0;
+    let mut sentences = vec![];
+    for end in sentence_starts(text) {
+        sentences.push(text[start..end].trim());
+        start = end;
+    }
+    sentences.push(text[start..].trim());
+
+    sentences.into_iter().filter(|x| !x.is_empty()).collect()
+}
+
+/// How the paragraphs of a file are laid out.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    sentence_per_line: bool,
+    max_sentences: Option<usize>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Puts every sentence of a paragraph on a line of its own.
+    pub fn set_sentence_per_line(&mut self, sentence_per_line: bool) -> &mut Self {
+        self.sentence_per_line = sentence_per_line;
+        self
+    }
+
+    /// Splits longer paragraphs into paragraphs of at most this many
+    /// sentences.
+    pub fn set_max_sentences(&mut self, max_sentences: usize) -> &mut Self {
+        self.max_sentences = Some(max_sentences.max(1));
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn sentence_per_line(&self) -> bool {
+        self.sentence_per_line
+    }
+
+    pub fn max_sentences(&self) -> Option<usize> {
+        self.max_sentences
+    }
+}
+
+/// Lays out a paragraph that has been joined into a single line.
+fn layout(paragraph: String, options: &Options) -> String {
+    if !options.sentence_per_line && options.max_sentences.is_none() {
+        return paragraph;
+    }
+
+    let sentences = sentences(&paragraph);
+    let separator = if options.sentence_per_line { "\n" } else { " " };
+    let max = options.max_sentences.unwrap_or(sentences.len()).max(1);
+
+    sentences.chunks(max)
+        .map(|group| group.join(separator))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 pub fn group_lines(contents: &str) -> String {
     multi_to_single(contents)
 }
 
 pub fn group_lines_file(contents: &str) -> String {
+    group_lines_file_with(contents, &Options::new())
+}
+
+pub fn group_lines_file_with(contents: &str, options: &Options) -> String {
     contents.lines()
         .map(|x| x.trim())
         .fold(String::new(), |acc, x| acc + &x + "\n")
         .split("\n\n")
         .map(multi_to_single)
         .filter(|x| !x.is_empty())
+        .map(|x| layout(x, options))
         .fold(String::new(), |acc, x| acc + &x + "\n\n")
         .trim_right()
         .to_string()
@@ -106,4 +272,46 @@ mod tests {
         assert_eq!(group_lines_file(contents), result);
     }
 
+    #[test]
+    fn sentence_boundaries() {
+        let text = "Dr. Smith paid $3.50, e.g. for tea. Was it worth it? \
+                    \"Yes.\" He left... and came back… Then J. R. R. Tolkien wrote.";
+        assert_eq!(sentences(text),
+                   vec!["Dr. Smith paid $3.50, e.g. for tea.",
+                        "Was it worth it?",
+                        "\"Yes.\"",
+                        "He left... and came back…",
+                        "Then J. R. R. Tolkien wrote."]);
+        assert_eq!(sentence_starts("One. "), vec![5]);
+        assert_eq!(sentences("The answer was no. Then see p. 5 and no. 6 of vol. 2."),
+                   vec!["The answer was no.", "Then see p. 5 and no. 6 of vol. 2."]);
+        assert_eq!(sentences("She got an A. Then John F. Kennedy spoke."),
+                   vec!["She got an A.", "Then John F. Kennedy spoke."]);
+        assert_eq!(sentences(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn ellipsis_at_end() {
+        assert_eq!(sentence_starts("Call the Dr... "), vec![15]);
+        assert_eq!(sentences("Call the Dr... Then wait..."),
+                   vec!["Call the Dr...", "Then wait..."]);
+        assert_eq!(sentence_starts("Call the Dr. "), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn sentence_per_line() {
+        let contents = "One. Two.\nThree.\n\nFour.";
+        let options = Options::new().set_sentence_per_line(true).build();
+        assert_eq!(group_lines_file_with(contents, &options),
+                   "One.\nTwo.\nThree.\n\nFour.".to_string());
+    }
+
+    #[test]
+    fn max_sentences() {
+        let contents = "One. Two. Three.\n\nFour.";
+        let options = Options::new().set_max_sentences(2).build();
+        assert_eq!(group_lines_file_with(contents, &options),
+                   "One. Two.\n\nThree.\n\nFour.".to_string());
+    }
+
 }
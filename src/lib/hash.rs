@@ -0,0 +1,19 @@
+/// FNV-1a, which is stable across runs, platforms and releases unlike the
+/// hashers in the standard library, so it can be stored.
+pub fn hash(contents: &[u8]) -> u64 {
+    contents.iter()
+        .fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -4,91 +4,228 @@ use ir;
 use super::html::Node;
 use super::html::Nodes;
 
-impl From<Node> for ir::IR {
-    fn from(node: Node) -> ir::IR {
-        match node {
-            Node::Text(x) => {
-                info!("Shouldn't really have a text node at the top level.");
+/// A block of the IR along with where it came from, if that is known.
+pub type Block = (ir::IR, Option<ir::Origin>);
 
-                ir::IR::from(convert_textblock(Nodes::from(Node::Text(x))))
-            }
-            Node::Element { tag, attributes, children } => {
-                match tag.as_str() {
-                    "img" => convert_img(attributes),
-                    "p" => convert_p(children),
-                    "pre" => convert_pre(children),
-                    "ol" => convert_ol(children),
-                    "ul" => convert_ul(children),
-                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
-                        convert_h(tag, children)
-                    }
-                    _ => {
-                        let msg = format!("Could not handle `{}` element", tag);
-                        //info!(msg);
+/// Converts a block element into the blocks of the IR. Most elements become a
+/// single block, and images that can't be shown become none. The origins of
+/// the blocks nested in lists and tables are kept in the origin of the block.
+pub fn convert_node(node: Node) -> Vec<Block> {
+    match node {
+        Node::Text(x) => {
+            info!("Shouldn't really have a text node at the top level.");
 
-                        ir::IR::pre(&msg)
-                    }
+            vec![(ir::IR::from(convert_textblock(Nodes::from(Node::Text(x)))), None)]
+        }
+        Node::Element { tag, attributes, children, line } => {
+            let origin = line.map(|line| ir::Origin::new(&tag, line));
+            let mut nested = vec![];
+            let blocks = match tag.as_str() {
+                "img" => convert_img(&attributes).map(ir::IR::from).into_iter().collect(),
+                "figure" => convert_figure(children),
+                "p" | "figcaption" => {
+                    return convert_p(children)
+                        .into_iter()
+                        .map(|(block, inner)| (block, inner.or_else(|| origin.clone())))
+                        .collect();
                 }
-            }
+                "footnote" => vec![convert_footnote(attributes, children)],
+                "pre" => vec![convert_pre(children)],
+                "ol" => vec![ir::IR::from(convert_ol(&attributes, children, &mut nested))],
+                "ul" => vec![ir::IR::from(convert_ul(children, &mut nested))],
+                "table" => vec![convert_table(children, &mut nested)],
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    vec![convert_h(tag, children)]
+                }
+                "strong" | "em" | "dfn" => {
+                    let node = Node::Element {
+                        tag,
+                        attributes,
+                        children,
+                        line: None,
+                    };
+
+                    vec![ir::IR::from(convert_textblock(Nodes::from(node)))]
+                }
+                _ => {
+                    let msg = format!("Could not handle `{}` element", tag);
+                    //info!(msg);
+
+                    vec![ir::IR::pre(&msg)]
+                }
+            };
+
+            let origin = origin.map(|origin| origin.set_nested(nested));
+            blocks.into_iter().map(|block| (block, origin.clone())).collect()
         }
     }
 }
 
-fn convert_h(tag: String, mut children: Nodes) -> ir::IR {
+fn convert_h(tag: String, children: Nodes) -> ir::IR {
     let digit = &tag[1..2];
     let level = usize::from_str_radix(digit, 10)
         .unwrap();
 
-    let text = children.pop()
-        .unwrap_or(Node::Text("".to_string()))
-        .unwrap_text()
-        .unwrap_or("".to_string());
+    let text = children.into_iter()
+        .map(Node::into_text)
+        .collect::<String>();
 
     ir::IR::header(level, text.trim())
 }
 
-fn convert_ol(children: Nodes) -> ir::IR {
-    ir::IR::from(convert_list(ir::ListType::Ordered, children))
+/// The origins of the blocks nested in the block being converted, in the order
+/// of `IR::nested`.
+type Nested = Vec<Option<ir::Origin>>;
+
+fn convert_ol(attributes: &[(String, String)], children: Nodes, nested: &mut Nested) -> ir::List {
+    convert_list(ir::ListType::Ordered(numbering(attributes)), children, nested)
 }
 
-fn convert_ul(children: Nodes) -> ir::IR {
-    ir::IR::from(convert_list(ir::ListType::Unordered, children))
+fn convert_ul(children: Nodes, nested: &mut Nested) -> ir::List {
+    convert_list(ir::ListType::Unordered, children, nested)
 }
 
-fn convert_pre(mut children: Nodes) -> ir::IR {
-    let node = children.pop().unwrap_or(Node::Text("".to_string()));
+/// The numbering of an `ol` from its `start`, `reversed` and `type`.
+fn numbering(attributes: &[(String, String)]) -> ir::Numbering {
+    let mut numbering = ir::Numbering::new();
+    if let Ok(start) = get_attribute(attributes, "start").trim().parse() {
+        numbering.set_start(start);
+    }
+    if attributes.iter().any(|(k, _)| k == "reversed") {
+        numbering.set_reversed(true);
+    }
 
-    let content = match node {
-        Node::Text(x) => x,
-        _ => {
-            println!("Pre tag has non-text as children");
-            String::new()
-        }
+    let style = match get_attribute(attributes, "type").trim() {
+        "a" => ir::NumberStyle::LowerAlpha,
+        "A" => ir::NumberStyle::UpperAlpha,
+        "i" => ir::NumberStyle::LowerRoman,
+        "I" => ir::NumberStyle::UpperRoman,
+        _ => ir::NumberStyle::Decimal,
     };
+    numbering.set_style(style);
+
+    numbering.build()
+}
+
+fn convert_pre(children: Nodes) -> ir::IR {
+    let content = children.into_iter()
+        .map(Node::into_text)
+        .collect::<String>();
 
     ir::IR::pre(&content)
 }
 
-fn convert_p(children: Nodes) -> ir::IR {
-    ir::IR::from(convert_textblock(children))
+fn get_attribute(attributes: &[(String, String)], name: &str) -> String {
+    attributes.iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default()
 }
 
-fn convert_img(attributes: Vec<(String, String)>) -> ir::IR {
-    let (_, src) = attributes.into_iter()
-        .find(|&(ref k, _)| k == "src")
-        .expect("img tag has no src attribute");
+fn convert_footnote(attributes: Vec<(String, String)>, children: Nodes) -> ir::IR {
+    // Paragraphs in the footnote are joined into one block of text
+    let children = children.into_iter()
+        .flat_map(|child| match child {
+            Node::Element { ref tag, .. } if tag == "p" => {
+                let mut nodes = vec![Node::Text(" ".to_string())];
+                nodes.extend(child.children());
+                nodes
+            }
+            child => vec![child],
+        })
+        .collect();
+
+    let id = get_attribute(&attributes, "id");
+    let label = get_attribute(&attributes, "label");
 
-    ir::IR::img(&src)
+    ir::IR::from(ir::Footnote::new(&id, &label, convert_textblock(children)))
 }
 
-fn convert_list(style: ir::ListType, items: Nodes) -> ir::List {
+/// A paragraph, split around the images in it so they become blocks of their
+/// own.
+fn convert_p(children: Nodes) -> Vec<Block> {
+    if children.iter().any(|child| is_block(child.tag())) {
+        convert_blocks(children)
+    } else {
+        vec![(ir::IR::from(convert_textblock(children)), None)]
+    }
+}
+
+/// The image, or `None` if it has no `src`, as with images that are only
+/// loaded by a script.
+fn convert_img(attributes: &[(String, String)]) -> Option<ir::Image> {
+    let src = match attributes.iter().find(|(k, _)| k == "src") {
+        Some((_, src)) => src,
+        None => {
+            warn!("Skipping an image without a `src`");
+            return None;
+        }
+    };
+
+    let mut image = ir::Image::new(src);
+    for (key, value) in attributes {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match key.as_str() {
+            "alt" => image.set_alt(value),
+            "title" => image.set_title(value),
+            "width" => image.set_width(value),
+            "height" => image.set_height(value),
+            _ => &mut image,
+        };
+    }
+
+    Some(image.build())
+}
+
+/// The image of a figure with its caption. The caption is kept as a paragraph
+/// if there is no image to give it to.
+fn convert_figure(children: Nodes) -> Vec<ir::IR> {
+    let mut image = None;
+    let mut caption = String::new();
+    for child in children {
+        match child.tag() {
+            "img" => {
+                if let Node::Element { ref attributes, .. } = child {
+                    image = convert_img(attributes);
+                }
+            }
+            "figcaption" => caption = child.into_text().trim().to_string(),
+            _ => (),
+        }
+    }
+
+    match image {
+        Some(mut image) => {
+            if !caption.is_empty() {
+                image.set_caption(&caption);
+            }
+
+            vec![ir::IR::from(image)]
+        }
+        None if !caption.is_empty() => vec![ir::IR::from(ir::TextBlock::from(caption.as_str()))],
+        None => vec![],
+    }
+}
+
+fn convert_list(style: ir::ListType, items: Nodes, nested: &mut Nested) -> ir::List {
     items.into_iter()
         .filter(|ref child| child.is_element())
         .map(|child| match child {
             Node::Text(_) => panic!("There should be no text now"),
-            Node::Element { tag, children, .. } => {
+            Node::Element { tag, attributes, children, .. } => {
                 match tag.as_str() {
-                    "li" => convert_list_item(children),
+                    "li" => {
+                        let mut item = convert_list_item(children, nested);
+                        if let Ok(value) = get_attribute(&attributes, "value").trim().parse() {
+                            item.set_value(value);
+                        }
+
+                        item
+                    }
                     _ => panic!("There is a non li tag in the list: {}", tag),
                 }
             }
@@ -97,82 +234,265 @@ fn convert_list(style: ir::ListType, items: Nodes) -> ir::List {
         .build()
 }
 
-fn convert_list_item(children: Nodes) -> ir::ListItem {
-    fn convert_element(tag: String, children: Nodes) -> ir::ListContent {
+/// The elements that become blocks of their own wherever they are.
+const BLOCKS: &[&str] = &["img", "figure", "pre", "table", "h1", "h2", "h3", "h4", "h5", "h6",
+                          "footnote"];
+
+/// The elements that only group blocks, and are replaced by their contents
+/// inside list items and table cells.
+const CONTAINERS: &[&str] = &["blockquote", "section", "article", "aside", "main", "details"];
+
+fn is_block(tag: &str) -> bool {
+    BLOCKS.contains(&tag) || CONTAINERS.contains(&tag) ||
+    ["p", "ul", "ol", "figcaption"].contains(&tag)
+}
+
+/// Converts the inline content collected so far, leaving it empty, or `None`
+/// if it is blank. The spaces at its ends are removed, as those next to images
+/// are kept by the whitespace pass.
+fn take_inline(inline: &mut Nodes) -> Option<ir::TextBlock> {
+    let mut nodes = ::std::mem::replace(inline, Nodes::new()).into_iter().collect::<Vec<_>>();
+    if let Some(&mut Node::Text(ref mut x)) = nodes.first_mut() {
+        *x = x.trim_start().to_string();
+    }
+    if let Some(&mut Node::Text(ref mut x)) = nodes.last_mut() {
+        *x = x.trim_end().to_string();
+    }
+    let text = convert_textblock(nodes.into_iter().collect());
+
+    if text.is_blank() { None } else { Some(text) }
+}
+
+fn convert_list_item(children: Nodes, nested: &mut Nested) -> ir::ListItem {
+    let mut item = ir::ListItem::new();
+    add_list_contents(&mut item, children, nested);
+
+    item.build()
+}
+
+/// Adds the children to the item, with each run of text and inline elements
+/// between the blocks as one text.
+fn add_list_contents(item: &mut ir::ListItem, children: Nodes, nested: &mut Nested) {
+    let mut inline = Nodes::new();
+    for child in children {
+        if !is_block(child.tag()) {
+            inline = inline.add(child);
+            continue;
+        }
+
+        if let Some(text) = take_inline(&mut inline) {
+            item.add(ir::ListContent::from(text));
+        }
+        let (tag, attributes, children, line) = match child {
+            Node::Element { tag, attributes, children, line } => (tag, attributes, children, line),
+            Node::Text(_) => unreachable!("Text is inline"),
+        };
         match tag.as_str() {
             "ol" => {
-                ir::ListContent::from(convert_list(ir::ListType::Ordered,
-                                                   children))
+                item.add(ir::ListContent::from(convert_ol(&attributes, children, nested)));
             }
             "ul" => {
-                ir::ListContent::from(convert_list(ir::ListType::Unordered,
-                                                   children))
+                item.add(ir::ListContent::from(convert_ul(children, nested)));
             }
-            "p" => ir::ListContent::from(convert_textblock(children)),
-            "sup" => {
-                let content = convert_textblock(children);
-                let sup = ir::Text::Sup(content);
-
-                ir::ListContent::from(ir::TextBlock::from(sup))
+            "p" | "figcaption" => {
+                for (block, origin) in convert_p(children) {
+                    match block {
+                        ir::IR::Par(text) => item.add(ir::ListContent::from(text)),
+                        block => {
+                            nested.push(origin);
+                            item.add(ir::ListContent::from(block))
+                        }
+                    };
+                }
             }
-            "code" => {
+            tag if CONTAINERS.contains(&tag) => add_list_contents(item, children, nested),
+            _ => {
                 let node = Node::Element {
                     tag,
+                    attributes,
                     children,
-                    attributes: vec![]
+                    line,
                 };
-                let nodes = Nodes::from(node);
-
-                ir::ListContent::from(convert_textblock(nodes))
+                for (block, origin) in convert_node(node) {
+                    item.add(ir::ListContent::from(block));
+                    nested.push(origin);
+                }
             }
-            "pre" => {
-                let node = Node::Element {
-                    tag: "code".to_string(),
-                    children,
-                    attributes: vec![],
-                };
-                let nodes = Nodes::from(node);
+        }
+    }
 
-                ir::ListContent::from(convert_textblock(nodes))
+    if let Some(text) = take_inline(&mut inline) {
+        item.add(ir::ListContent::from(text));
+    }
+}
+
+fn convert_table(children: Nodes, nested: &mut Nested) -> ir::IR {
+    let mut table = ir::Table::new();
+    let mut body = vec![];
+    // The origins of the blocks in the header, body and footer, which are
+    // nested in that order whatever the order of the rows in the input.
+    let (mut header, mut footer) = (vec![], vec![]);
+    for child in children {
+        let section = match child.tag() {
+            "thead" | "tbody" | "tfoot" => child.tag().to_string(),
+            "tr" => "tbody".to_string(),
+            _ => continue,
+        };
+        let rows = if child.tag() == "tr" {
+            vec![child]
+        } else {
+            child.children().into_iter().filter(|row| row.tag() == "tr").collect()
+        };
+
+        for row in rows {
+            let is_header = match row {
+                Node::Element { ref children, .. } => {
+                    children.iter().filter(|cell| cell.is_element()).all(|cell| cell.tag() == "th")
+                }
+                Node::Text(_) => false,
+            };
+            let (row, origins) = convert_row(row);
+            match section.as_str() {
+                "thead" => {
+                    table.set_header(row);
+                    header = origins;
+                }
+                "tfoot" => {
+                    table.set_footer(row);
+                    footer = origins;
+                }
+                _ if is_header && body.is_empty() && table.header_ref().is_none() => {
+                    table.set_header(row);
+                    header = origins;
+                }
+                _ => body.push((row, origins)),
             }
-            _ => panic!("Found {} tag in a list", tag),
         }
     }
-    children.into_iter()
-        .map(|child| match child {
-            Node::Text(x) => ir::ListContent::from(x),
-            Node::Element { tag, children, .. } => {
-                convert_element(tag, children)
-            }
+
+    nested.extend(header);
+    for (row, origins) in body {
+        table.add(row);
+        nested.extend(origins);
+    }
+    nested.extend(footer);
+
+    ir::IR::from(table.build())
+}
+
+fn convert_row(row: Node) -> (ir::TableRow, Nested) {
+    let mut nested = vec![];
+    let row = row.children()
+        .into_iter()
+        .filter(|cell| cell.tag() == "td" || cell.tag() == "th")
+        .map(|cell| {
+            let (blocks, origins): (Vec<_>, Vec<_>) = convert_blocks(cell.children())
+                .into_iter()
+                .unzip();
+            nested.extend(origins);
+
+            ir::TableCell::blocks(blocks)
         })
-        .fold(&mut ir::ListItem::new(), |item, content| item.add(content))
-        .build()
+        .fold(&mut ir::TableRow::new(), |row, cell| row.add(cell))
+        .build();
+
+    (row, nested)
+}
+
+/// Converts mixed inline and block content, with each run of inline content
+/// between blocks becoming a paragraph.
+fn convert_blocks(children: Nodes) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut inline = Nodes::new();
+    for child in children {
+        if !is_block(child.tag()) {
+            inline = inline.add(child);
+            continue;
+        }
+
+        blocks.extend(take_inline(&mut inline).map(|text| (ir::IR::from(text), None)));
+        if CONTAINERS.contains(&child.tag()) {
+            blocks.extend(convert_blocks(child.children()));
+        } else {
+            blocks.extend(convert_node(child));
+        }
+    }
+    blocks.extend(take_inline(&mut inline).map(|text| (ir::IR::from(text), None)));
+
+    blocks
 }
 
 fn convert_textblock(nodes: Nodes) -> ir::TextBlock {
     nodes.into_iter()
-        .map(|node| match node {
-            Node::Text(x) => ir::Text::text(&x),
-            Node::Element { tag, mut children, .. } => {
-                match tag.as_str() {
-                    "sup" => ir::Text::Sup(convert_textblock(children)),
-                    "sub" => ir::Text::Sub(convert_textblock(children)),
-                    "code" => {
-                        let node = children.pop().unwrap_or(Node::Text("".to_string()));
-
-                        let content = match node {
-                            Node::Text(x) => x,
-                            _ => {
-                                println!("code tag has non-text as children");
-                                String::new()
-                            }
-                        };
-                        ir::Text::Code(content)
-                    }
-                    _ => ir::Text::text("lmoa"),
-                }
+        .flat_map(|node| match node {
+            Node::Text(x) => vec![ir::Text::text(&x)],
+            Node::Element { tag, attributes, children, .. } => {
+                convert_inline(tag, attributes, children)
             }
         })
         .fold(&mut ir::TextBlock::new(), |block, node| block.add(node))
         .build()
 }
+
+/// Converts an inline element. Unknown elements are replaced by their
+/// contents.
+fn convert_inline(tag: String,
+                  attributes: Vec<(String, String)>,
+                  children: Nodes)
+                  -> Vec<ir::Text> {
+    let text = match tag.as_str() {
+        "noteref" => {
+            let id = get_attribute(&attributes, "href");
+            let label = children.into_iter()
+                .map(Node::into_text)
+                .collect::<String>();
+
+            ir::Text::footnote_ref(&id, &label)
+        }
+        "sup" => ir::Text::Sup(convert_textblock(children)),
+        "sub" => ir::Text::Sub(convert_textblock(children)),
+        "strong" => ir::Text::Strong(convert_textblock(children)),
+        "em" => ir::Text::Emphasis(convert_textblock(children)),
+        "dfn" => ir::Text::Definition(convert_textblock(children)),
+        "code" => {
+            let content = children.into_iter()
+                .map(Node::into_text)
+                .collect::<String>();
+
+            ir::Text::Code(content)
+        }
+        "br" => ir::Text::LineBreak,
+        "img" => {
+            warn!("Keeping only the alt text of an image inside inline text");
+
+            let alt = get_attribute(&attributes, "alt");
+            if alt.trim().is_empty() {
+                return vec![];
+            }
+
+            ir::Text::text(alt.trim())
+        }
+        "wbr" => return vec![],
+        "kbd" => ir::Text::Keyboard(convert_textblock(children)),
+        "var" => ir::Text::Variable(convert_textblock(children)),
+        "samp" => ir::Text::Sample(convert_textblock(children)),
+        "abbr" | "acronym" => {
+            let title = attributes.iter()
+                .find(|(k, _)| k == "title")
+                .map(|(_, v)| v.trim().to_string())
+                .filter(|title| !title.is_empty());
+
+            ir::Text::Abbreviation(title, convert_textblock(children))
+        }
+        "del" | "s" | "strike" => ir::Text::Deleted(convert_textblock(children)),
+        "ins" => ir::Text::Inserted(convert_textblock(children)),
+        "small" => ir::Text::Small(convert_textblock(children)),
+        _ => {
+            warn!("Keeping only the text of an unknown `{}` element", tag);
+
+            return convert_textblock(children).into_iter().collect();
+        }
+    };
+
+    vec![text]
+}
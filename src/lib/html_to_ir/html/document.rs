@@ -1,8 +1,9 @@
 use super::Nodes;
 use super::Node;
 
-use super::super::optimize;
-//use super::super::convert;
+use super::super::metadata;
+use super::super::pass::Pipeline;
+use super::super::convert::convert_node;
 
 use ir;
 
@@ -21,20 +22,22 @@ impl Document {
     }
 
     pub fn convert(self) -> ir::Document {
-        let doc = self.children
-            .into_iter()
-            .flat_map(|child| optimize::remove_tags(child))
-            .collect::<Nodes>();
-        debug!("Removed tags: {:#?}", doc);
+        self.convert_with(&Pipeline::default())
+    }
 
-        let doc = doc.into_iter()
-            .flat_map(|child| optimize::handle_containers(child))
-            .collect::<Nodes>();
-        debug!("handle_containers: {:#?}", doc);
+    pub fn metadata(&self) -> ir::Metadata {
+        metadata::extract(&self.children)
+    }
+
+    /// Converts the document after running it through the passes.
+    pub fn convert_with(self, pipeline: &Pipeline) -> ir::Document {
+        let metadata = self.metadata();
+        let doc = pipeline.run(self.children);
 
         let doc = doc.into_iter()
-            .map(|child| child.into())
-            .collect::<ir::Document>();
+            .flat_map(convert_node)
+            .fold(ir::Document::new(), |doc, (block, origin)| doc.add_from(block, origin))
+            .set_metadata(metadata);
         debug!("convert: {:#?}", doc);
 
         doc
@@ -0,0 +1,133 @@
+extern crate html5ever;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use self::html5ever::QualName;
+use self::html5ever::rcdom::{Handle, RcDom};
+use self::html5ever::tendril::StrTendril;
+use self::html5ever::tokenizer::Attribute;
+use self::html5ever::tree_builder::{NodeOrText, QuirksMode, TreeSink};
+
+/// The line each element of a parsed document started on, keyed by its node.
+#[derive(Default)]
+pub struct Lines {
+    lines: HashMap<usize, usize>,
+}
+
+impl Lines {
+    fn key(handle: &Handle) -> usize {
+        Rc::as_ptr(handle) as usize
+    }
+
+    pub fn get(&self, handle: &Handle) -> Option<usize> {
+        self.lines.get(&Lines::key(handle)).cloned()
+    }
+}
+
+/// An `RcDom` that remembers the line of every element it creates. The parser
+/// only reports lines, so there is no column or byte offset to keep.
+pub struct LineDom {
+    pub dom: RcDom,
+    pub lines: Lines,
+    line: u64,
+}
+
+impl Default for LineDom {
+    fn default() -> Self {
+        LineDom {
+            dom: RcDom::default(),
+            lines: Lines::default(),
+            line: 1,
+        }
+    }
+}
+
+impl TreeSink for LineDom {
+    type Output = Self;
+
+    fn finish(self) -> Self {
+        self
+    }
+
+    type Handle = Handle;
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.dom.parse_error(msg)
+    }
+
+    fn get_document(&mut self) -> Handle {
+        self.dom.get_document()
+    }
+
+    fn get_template_contents(&mut self, target: Handle) -> Handle {
+        self.dom.get_template_contents(target)
+    }
+
+    fn same_node(&self, x: Handle, y: Handle) -> bool {
+        self.dom.same_node(x, y)
+    }
+
+    fn elem_name(&self, target: Handle) -> QualName {
+        self.dom.elem_name(target)
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.dom.set_quirks_mode(mode)
+    }
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attribute>) -> Handle {
+        let handle = self.dom.create_element(name, attrs);
+        self.lines.lines.insert(Lines::key(&handle), self.line as usize);
+
+        handle
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> Handle {
+        self.dom.create_comment(text)
+    }
+
+    fn has_parent_node(&self, node: Handle) -> bool {
+        self.dom.has_parent_node(node)
+    }
+
+    fn append(&mut self, parent: Handle, child: NodeOrText<Handle>) {
+        self.dom.append(parent, child)
+    }
+
+    fn append_before_sibling(&mut self, sibling: Handle, child: NodeOrText<Handle>) {
+        self.dom.append_before_sibling(sibling, child)
+    }
+
+    fn append_doctype_to_document(&mut self,
+                                  name: StrTendril,
+                                  public_id: StrTendril,
+                                  system_id: StrTendril) {
+        self.dom.append_doctype_to_document(name, public_id, system_id)
+    }
+
+    fn add_attrs_if_missing(&mut self, target: Handle, attrs: Vec<Attribute>) {
+        self.dom.add_attrs_if_missing(target, attrs)
+    }
+
+    fn remove_from_parent(&mut self, target: Handle) {
+        self.dom.remove_from_parent(target)
+    }
+
+    fn reparent_children(&mut self, node: Handle, new_parent: Handle) {
+        self.dom.reparent_children(node, new_parent)
+    }
+
+    fn mark_script_already_started(&mut self, node: Handle) {
+        self.dom.mark_script_already_started(node)
+    }
+
+    fn is_mathml_annotation_xml_integration_point(&self, handle: Handle) -> bool {
+        self.dom.is_mathml_annotation_xml_integration_point(handle)
+    }
+
+    fn set_current_line(&mut self, line: u64) {
+        self.line = line;
+    }
+}
@@ -12,7 +12,10 @@ pub use self::nodes::Nodes;
 mod document;
 pub use self::document::Document;
 
-pub fn convert_dom(handle: &Handle) -> Result<Document, &'static str> {
+mod lines;
+pub use self::lines::{LineDom, Lines};
+
+pub fn convert_dom(handle: &Handle, lines: &Lines) -> Result<Document, &'static str> {
     let node = handle.borrow();
 
     use self::html5ever::rcdom::NodeEnum;
@@ -20,7 +23,7 @@ pub fn convert_dom(handle: &Handle) -> Result<Document, &'static str> {
         NodeEnum::Document => {
             Ok(node.children
                 .iter()
-                .map(Node::from)
+                .map(|child| Node::from_handle(child, lines))
                 .fold(Document::new(), |doc, child| doc.add(child)))
         }
         _ => Err("Converting from non-Document node"),
@@ -2,15 +2,19 @@ extern crate html5ever;
 
 use self::html5ever::rcdom::Handle;
 
+use super::Lines;
 use super::Nodes;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Node {
     Text(String),
     Element {
         tag: String,
         attributes: Vec<(String, String)>,
         children: Nodes,
+        /// The line of the input the element started on, if it came from
+        /// there.
+        line: Option<usize>,
     },
 }
 
@@ -35,10 +39,69 @@ impl Node {
             _ => None,
         }
     }
+
+    pub fn children(self) -> Nodes {
+        match self {
+            Node::Text(_) => Nodes::new(),
+            Node::Element { children, .. } => children,
+        }
+    }
+
+    /// The tag of the element, which is empty for text.
+    pub fn tag(&self) -> &str {
+        match *self {
+            Node::Text(_) => "",
+            Node::Element { ref tag, .. } => tag,
+        }
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            Node::Text(_) => None,
+            Node::Element { line, .. } => line,
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        match *self {
+            Node::Text(_) => None,
+            Node::Element { ref attributes, .. } => {
+                attributes.iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.as_str())
+            }
+        }
+    }
+
+    /// Finds the elements with the tag among this node and its descendants.
+    pub fn find<'a>(&'a self, tag: &str) -> Vec<&'a Node> {
+        match *self {
+            Node::Text(_) => vec![],
+            Node::Element { tag: ref name, ref children, .. } => {
+                let mut found = if name == tag { vec![self] } else { vec![] };
+                found.extend(children.iter().flat_map(|child| child.find(tag)));
+
+                found
+            }
+        }
+    }
+
+    /// Concatenates the text of this node and all of its descendants.
+    pub fn into_text(self) -> String {
+        match self {
+            Node::Text(x) => x,
+            Node::Element { children, .. } => {
+                children.into_iter()
+                    .map(Node::into_text)
+                    .collect()
+            }
+        }
+    }
 }
 
-impl<'a> From<&'a Handle> for Node {
-    fn from(handle: &Handle) -> Self {
+impl Node {
+    /// Converts a parsed node, taking the line of each element from `lines`.
+    pub fn from_handle(handle: &Handle, lines: &Lines) -> Self {
         let node = handle.borrow();
 
         use self::html5ever::rcdom::NodeEnum;
@@ -56,13 +119,14 @@ impl<'a> From<&'a Handle> for Node {
 
                 let children = node.children
                     .iter()
-                    .map(|child| Node::from(child))
+                    .map(|child| Node::from_handle(child, lines))
                     .collect();
 
                 Node::Element {
                     tag: tag,
                     attributes: attributes,
                     children: children,
+                    line: lines.get(handle),
                 }
             }
             NodeEnum::Text(ref text) => Node::Text(text.as_ref().to_string()),
@@ -2,7 +2,7 @@ use super::Node;
 
 use std::iter::FromIterator;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Nodes {
     nodes: Vec<Node>,
 }
@@ -41,6 +41,10 @@ impl Nodes {
     pub fn pop(&mut self) -> Option<Node> {
         self.nodes.pop()
     }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Node> {
+        self.nodes.iter()
+    }
 }
 
 impl FromIterator<Node> for Nodes {
@@ -0,0 +1,167 @@
+extern crate serde_json;
+
+use std::collections::HashMap;
+
+use self::serde_json::Value;
+
+use ir;
+use super::html::Node;
+use super::html::Nodes;
+
+/// The values found in the document, keyed by where they were found, such as
+/// `og:title` or `dc.creator`. The first value of each key is kept.
+#[derive(Default)]
+struct Found {
+    values: HashMap<String, String>,
+}
+
+impl Found {
+    fn insert(&mut self, key: &str, value: &str) {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !value.is_empty() {
+            self.values.entry(key.to_lowercase()).or_insert(value);
+        }
+    }
+
+    /// The value from the first of the keys that was found.
+    fn first(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .filter_map(|key| self.values.get(*key))
+            .map(|value| value.as_str())
+            .next()
+    }
+
+    fn collect(&mut self, node: &Node) {
+        let (tag, children) = match *node {
+            Node::Element { ref tag, ref children, .. } => (tag.as_str(), children),
+            Node::Text(_) => return,
+        };
+
+        match tag {
+            "html" => {
+                if let Some(lang) = node.attribute("lang") {
+                    self.insert("html:lang", lang);
+                }
+            }
+            "title" => self.insert("title", &node.clone().into_text()),
+            "meta" => {
+                let key = node.attribute("property")
+                    .or_else(|| node.attribute("name"))
+                    .or_else(|| node.attribute("itemprop"));
+                if let (Some(key), Some(content)) = (key, node.attribute("content")) {
+                    let key = key.to_lowercase().replace("dcterms.", "dc.");
+                    self.insert(&key, content);
+                }
+                if let Some(language) = node.attribute("http-equiv")
+                    .filter(|x| x.eq_ignore_ascii_case("content-language"))
+                    .and_then(|_| node.attribute("content")) {
+                    self.insert("content-language", language);
+                }
+            }
+            "link" => {
+                let canonical = node.attribute("rel")
+                    .is_some_and(|rel| rel.split_whitespace().any(|x| x == "canonical"));
+                if let (true, Some(href)) = (canonical, node.attribute("href")) {
+                    self.insert("canonical", href);
+                }
+            }
+            "script" => {
+                if node.attribute("type") == Some("application/ld+json") {
+                    let json = node.clone().into_text();
+                    match serde_json::from_str::<Value>(&json) {
+                        Ok(value) => self.json_ld(&value),
+                        Err(err) => warn!("Invalid JSON-LD: {}", err),
+                    }
+                }
+                return;
+            }
+            _ => (),
+        }
+
+        for child in children.iter() {
+            self.collect(child);
+        }
+    }
+
+    /// Reads the fields of an article or web page in JSON-LD, which can be a
+    /// single object, a list of objects, or objects in an `@graph`.
+    fn json_ld(&mut self, value: &Value) {
+        match *value {
+            Value::Array(ref values) => {
+                for value in values {
+                    self.json_ld(value);
+                }
+            }
+            Value::Object(ref object) => {
+                if let Some(graph) = object.get("@graph") {
+                    self.json_ld(graph);
+                }
+
+                let fields = [("headline", "ld:title"),
+                              ("name", "ld:name"),
+                              ("author", "ld:author"),
+                              ("datePublished", "ld:published"),
+                              ("url", "ld:url"),
+                              ("inLanguage", "ld:language"),
+                              ("description", "ld:description"),
+                              ("publisher", "ld:publisher")];
+                for &(field, key) in &fields {
+                    if let Some(value) = object.get(field).and_then(json_ld_text) {
+                        self.insert(key, &value);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The text of a JSON-LD value, which can be a string, an object with a name,
+/// or a list of either.
+fn json_ld_text(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref x) => Some(x.clone()),
+        Value::Object(ref object) => object.get("name").and_then(json_ld_text),
+        Value::Array(ref values) => {
+            let values = values.iter().filter_map(json_ld_text).collect::<Vec<_>>();
+            if values.is_empty() {
+                None
+            } else {
+                Some(values.join(", "))
+            }
+        }
+        _ => None,
+    }
+}
+
+type Setter = for<'a> fn(&'a mut ir::Metadata, &str) -> &'a mut ir::Metadata;
+
+/// Reads the metadata of the document from its `title`, `meta` and `link`
+/// tags, along with any OpenGraph, Dublin Core and JSON-LD.
+pub fn extract(nodes: &Nodes) -> ir::Metadata {
+    let mut found = Found::default();
+    for node in nodes.iter() {
+        found.collect(node);
+    }
+
+    let mut metadata = ir::Metadata::new();
+    let fields: [(&[&str], Setter); 7] =
+        [(&["og:title", "ld:title", "dc.title", "title", "ld:name"], ir::Metadata::set_title),
+         (&["author", "ld:author", "dc.creator", "article:author"], ir::Metadata::set_author),
+         (&["article:published_time", "ld:published", "dc.date", "date"],
+          ir::Metadata::set_published),
+         (&["canonical", "og:url", "ld:url"], ir::Metadata::set_url),
+         (&["html:lang", "content-language", "dc.language", "og:locale", "ld:language"],
+          ir::Metadata::set_language),
+         (&["description", "og:description", "dc.description", "ld:description"],
+          ir::Metadata::set_description),
+         (&["og:site_name", "ld:publisher", "dc.publisher"], ir::Metadata::set_site_name)];
+
+    for &(keys, set) in &fields {
+        if let Some(value) = found.first(keys) {
+            set(&mut metadata, value);
+        }
+    }
+
+    metadata
+}
@@ -1,31 +1,48 @@
 extern crate html5ever;
 extern crate itertools;
-//extern crate log;
 
 use self::html5ever::parse_document;
-use self::html5ever::rcdom::RcDom;
 use self::html5ever::tendril::TendrilSink;
 
 pub mod html;
 mod optimize;
 mod convert;
+mod metadata;
+mod text;
+pub mod pass;
+pub mod selector;
 
 use ir;
 
 pub fn convert_file(contents: &str) -> ir::Document {
-    let dom = parse_document(RcDom::default(), Default::default()).one(contents);
+    convert_file_with(contents, &pass::Pipeline::default())
+}
+
+/// Converts the file with a custom pipeline of passes.
+pub fn convert_file_with(contents: &str, pipeline: &pass::Pipeline) -> ir::Document {
+    let dom = parse_document(html::LineDom::default(), Default::default()).one(contents);
 
-    let doc = html::convert_dom(&dom.document);
+    let doc = html::convert_dom(&dom.dom.document, &dom.lines);
 
     match doc {
-        Ok(doc) => doc.convert(),
+        Ok(doc) => doc.convert_with(pipeline),
         Err(err) => {
-            println!("Failed to convert document: Reason:\n{}", err);
-            return ir::Document::new();
+            warn!("Failed to convert document: Reason:\n{}", err);
+            ir::Document::new()
         }
     }
 }
 
+/// Reads only the metadata of the file, such as its title and canonical URL.
+pub fn metadata(contents: &str) -> ir::Metadata {
+    let dom = parse_document(html::LineDom::default(), Default::default()).one(contents);
+
+    match html::convert_dom(&dom.dom.document, &dom.lines) {
+        Ok(doc) => doc.metadata(),
+        Err(_) => ir::Metadata::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,6 +74,61 @@ mod tests {
         assert_eq!(convert_file(content), result);
     }
 
+    #[test]
+    fn metadata() {
+        let content = r#"<html lang="en"><head>
+            <title>Cell - Wikipedia</title>
+            <meta name="author" content="Jane Doe">
+            <meta property="og:title" content="Cell">
+            <meta property="og:site_name" content="Wikipedia">
+            <link rel="canonical" href="https://en.wikipedia.org/wiki/Cell">
+            <script type="application/ld+json">
+                {"@graph": [{"datePublished": "2020-01-02", "description": "A unit"}]}
+            </script>
+            </head><body><p>a</p></body></html>"#;
+        let metadata = ir::Metadata::new()
+            .set_title("Cell")
+            .set_author("Jane Doe")
+            .set_published("2020-01-02")
+            .set_url("https://en.wikipedia.org/wiki/Cell")
+            .set_language("en")
+            .set_description("A unit")
+            .set_site_name("Wikipedia")
+            .build();
+        let result = ir::Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .set_metadata(metadata);
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn origins() {
+        let content = "<html><body>\n<h1>a</h1>\n<div>\n<p>b</p>\n</div>b\n</body></html>";
+        let origins = convert_file(content).origins()
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        assert_eq!(origins, vec![Origin::new("h1", 2), Origin::new("p", 4)]);
+    }
+
+    #[test]
+    fn nested_origins() {
+        let content = "<html><body>\n<ul>\n<li>a\n<pre>b</pre></li>\n<li><table>\n\
+                       <tr><td>c</td><td><pre>d</pre></td></tr>\n</table></li>\n</ul>\n\
+                       </body></html>";
+        let doc = convert_file(content);
+        let list = doc.origin(0).unwrap();
+        assert_eq!((list.tag(), list.line()), ("ul", 2));
+
+        let nested = list.nested().iter().flatten().collect::<Vec<_>>();
+        assert_eq!(nested.iter().map(|x| (x.tag(), x.line())).collect::<Vec<_>>(),
+                   vec![("pre", 4), ("table", 5)]);
+        assert_eq!(nested[1].nested(), &[None, Some(Origin::new("pre", 6))]);
+        assert_eq!(doc.iter().next().unwrap().nested().len(), 2);
+    }
+
     #[test]
     fn head_with_script() {
         let content = "<html><head><script></script></head></html>";
@@ -87,6 +159,87 @@ mod tests {
         assert_eq!(convert_file(content), result);
     }
 
+    #[test]
+    fn inline_img() {
+        let content = &body("<p>Text <img src=\"a.png\" /> more</p>\
+                             <ul><li><p>x <img src=\"b.png\" /></p></li></ul>\
+                             <p><strong>y <img src=\"c.png\" alt=\"c\" /></strong></p>");
+        let item = ListItem::new()
+            .add(ListContent::from(TextBlock::from("x")))
+            .add(ListContent::from(IR::img("b.png")))
+            .build();
+        let result = Document::new()
+            .add(IR::from(TextBlock::from("Text")))
+            .add(IR::img("a.png"))
+            .add(IR::from(TextBlock::from("more")))
+            .add(IR::from(List::new(ListType::Unordered).add(item).build()))
+            .add(IR::from(TextBlock::from(Text::Strong(TextBlock::new()
+                .add(Text::text("y "))
+                .add(Text::text("c"))
+                .build()))));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn img_attributes() {
+        let content = &body("<img src=\"a.png\" alt=\"a\" title=\"t\" width=\"10\" \
+                             height=\"20\" />");
+        let image = Image::new("a.png")
+            .set_alt("a")
+            .set_title("t")
+            .set_width("10")
+            .set_height("20")
+            .build();
+        let result = Document::new().add(IR::from(image));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn figure() {
+        let content = &body("<figure><a href=\"b.png\"><img src=\"a.png\" /></a>\
+                             <figcaption> Figure <b>1</b> </figcaption></figure>");
+        let image = Image::new("a.png")
+            .set_caption("Figure 1")
+            .build();
+        let result = Document::new().add(IR::from(image));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn figure_content() {
+        let content = &body("<figure><p>Before</p><img src=\"a.png\" /><p>After</p>\
+                             <figcaption>Caption</figcaption></figure>\
+                             <figure><img src=\"b.png\" /><img src=\"c.png\" />\
+                             <figcaption>Both</figcaption></figure>");
+        let result = Document::new()
+            .add(IR::from(TextBlock::from("Before")))
+            .add(IR::from(Image::new("a.png").set_caption("Caption").build()))
+            .add(IR::from(TextBlock::from("After")))
+            .add(IR::img("b.png"))
+            .add(IR::img("c.png"))
+            .add(IR::from(TextBlock::from("Both")));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn img_without_src() {
+        let content = &body("<img data-src=\"a.png\" /><p>a</p>\
+                             <figure><img /><figcaption>b</figcaption></figure>");
+        let result = Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .add(IR::from(TextBlock::from("b")));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn figure_without_image() {
+        let content = &body("<figure><pre>x</pre><figcaption>Listing</figcaption></figure>");
+        let result = Document::new()
+            .add(IR::pre("x"))
+            .add(IR::from(TextBlock::from("Listing")));
+        assert_eq!(convert_file(content), result);
+    }
+
     #[test]
     fn pre() {
         let content = &body("<pre><span>x</span> = <span>1</span>\n\nx</pre>");
@@ -97,7 +250,7 @@ mod tests {
     #[test]
     fn ol() {
         let content = &body("<ol><li>a</li><li>b</li></ol>");
-        let ul = List::new(ListType::Ordered)
+        let ul = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from(TextBlock::from("a")))
                 .build())
@@ -127,12 +280,12 @@ mod tests {
     #[test]
     fn ol_ol() {
         let content = &body("<ol><li>a<ol><li>aa</li></ol></li></ol>");
-        let inner_ul = List::new(ListType::Ordered)
+        let inner_ul = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from("aa"))
                 .build())
             .build();
-        let outer_ul = List::new(ListType::Ordered)
+        let outer_ul = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from("a"))
                 .add(ListContent::from(inner_ul))
@@ -150,7 +303,7 @@ mod tests {
                 .add(ListContent::from("aa"))
                 .build())
             .build();
-        let outer_ul = List::new(ListType::Ordered)
+        let outer_ul = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from("a"))
                 .add(ListContent::from(inner_ul))
@@ -163,7 +316,7 @@ mod tests {
     #[test]
     fn ul_ol() {
         let content = &body("<ul><li>a<ol><li>aa</li></ol></li></ul>");
-        let inner_ul = List::new(ListType::Ordered)
+        let inner_ul = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from("aa"))
                 .build())
@@ -196,9 +349,192 @@ mod tests {
         assert_eq!(convert_file(content), result);
     }
 
+    #[test]
+    fn strong() {
+        let content = &body("<p><b>a</b> is <em>b</em></p>");
+        let text = TextBlock::new()
+            .add(Text::strong("a"))
+            .add(Text::text(" is "))
+            .add(Text::emphasis("b"))
+            .build();
+        let result = Document::new().add(IR::from(text));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn inline_elements() {
+        let content = &body("<p>a<br>b <kbd>c</kbd> <abbr title=\"d e\">DE</abbr> \
+                             <time>f</time> <blink>g</blink></p>\
+                             <ul><li>h<br>i</li></ul>");
+        let text = TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::LineBreak)
+            .add(Text::text("b "))
+            .add(Text::keyboard("c"))
+            .add(Text::text(" "))
+            .add(Text::abbreviation("DE", Some("d e")))
+            .add(Text::text(" f g"))
+            .build();
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::item(TextBlock::new()
+                .add(Text::text("h"))
+                .add(Text::LineBreak)
+                .add(Text::text("i"))
+                .build()))
+            .build();
+        let result = Document::new()
+            .add(IR::from(text))
+            .add(IR::from(list));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn inline_runs_in_list_items() {
+        let content = &body("<ul><li>Use <code>x</code> now<sub>2</sub>\
+                             <ul><li>a</li></ul>b</li></ul>");
+        let text = TextBlock::new()
+            .add(Text::text("Use "))
+            .add(Text::code("x"))
+            .add(Text::text(" now"))
+            .add(Text::sub("2"))
+            .build();
+        let inner = List::new(ListType::Unordered)
+            .add(ListItem::item(TextBlock::from("a")))
+            .build();
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::new()
+                .add(ListContent::from(text))
+                .add(ListContent::from(inner))
+                .add(ListContent::from("b"))
+                .build())
+            .build();
+        let result = Document::new().add(IR::from(list));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn blocks_in_list_items() {
+        let content = &body("<ul><li>a<pre>b</pre><img src=\"c.png\"></li>\
+                             <li><blockquote><h2>d</h2></blockquote></li></ul>");
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::new()
+                .add(ListContent::from("a"))
+                .add(ListContent::from(IR::pre("b")))
+                .add(ListContent::from(IR::img("c.png")))
+                .build())
+            .add(ListItem::new()
+                .add(ListContent::from(IR::header(2, "d")))
+                .build())
+            .build();
+        let result = Document::new().add(IR::from(list));
+        assert_eq!(convert_file(content), result);
+    }
+
     #[test]
     fn table() {
+        let content = &body("<table><tr><th>a</th><th>b</th></tr>\
+                             <tr><td>c</td><td>d<ul><li>e</li></ul></td></tr></table>");
+        let header = TableRow::new()
+            .add(TableCell::new(TextBlock::from("a")))
+            .add(TableCell::new(TextBlock::from("b")))
+            .build();
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::item(TextBlock::from("e")))
+            .build();
+        let row = TableRow::new()
+            .add(TableCell::new(TextBlock::from("c")))
+            .add(TableCell::blocks(vec![IR::from(TextBlock::from("d")), IR::from(list)]))
+            .build();
+        let table = Table::new()
+            .set_header(header)
+            .add(row)
+            .build();
+        let result = Document::new().add(IR::from(table));
+        assert_eq!(convert_file(content), result);
+    }
 
+    #[test]
+    fn ordered_list_attributes() {
+        let content = &body("<ol start=\"4\" type=\"i\" reversed>\
+                             <li>a</li><li value=\"2\">b</li></ol>");
+        let numbering = Numbering::new()
+            .set_start(4)
+            .set_reversed(true)
+            .set_style(NumberStyle::LowerRoman)
+            .build();
+        let list = List::new(ListType::Ordered(numbering))
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::new().add(ListContent::from("b")).set_value(2).build())
+            .build();
+        assert_eq!(list.numbers(), vec![4, 2]);
+
+        let result = Document::new().add(IR::from(list));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn dl() {
+        let content = &body("<dl><dt>a</dt><dd>b</dd></dl>");
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::item(TextBlock::from(Text::definition("a"))))
+            .add(ListItem::item(TextBlock::from("b")))
+            .build();
+        let result = Document::new().add(IR::from(list));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn header_with_emphasis() {
+        let content = &body("<h2>a <em>b</em></h2>");
+        let result = Document::new().add(IR::header(2, "a b"));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn footnotes() {
+        let content = &body("<p>a<sup id=\"r1\"><a href=\"#fn1\">1</a></sup></p>\
+                             <section class=\"footnotes\"><hr /><ol>\
+                             <li id=\"fn1\"><p>note <a href=\"#r1\">↩</a></p></li>\
+                             </ol></section>");
+        let text = TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::footnote_ref("fn1", "1"))
+            .build();
+        let note = Footnote::new("fn1", "1", TextBlock::from(" note"));
+        let result = Document::new()
+            .add(IR::from(text))
+            .add(IR::from(note));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn heading_is_not_footnote() {
+        let content = &body("<p>see<sup><a href=\"#sec\">A</a></sup></p>\
+                             <h2 id=\"sec\">Appendix</h2><p>b</p>");
+        let text = TextBlock::new()
+            .add(Text::text("see"))
+            .add(Text::Sup(TextBlock::from("A")))
+            .build();
+        let result = Document::new()
+            .add(IR::from(text))
+            .add(IR::header(2, "Appendix"))
+            .add(IR::from(TextBlock::from("b")));
+        assert_eq!(convert_file(content), result);
+    }
+
+    #[test]
+    fn epub_footnotes() {
+        let content = &body("<p>a<a epub:type=\"noteref\" href=\"notes.xhtml#n1\">*</a></p>\
+                             <aside epub:type=\"footnote\" id=\"n1\">note</aside>");
+        let text = TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::footnote_ref("n1", "*"))
+            .build();
+        let note = Footnote::new("n1", "*", TextBlock::from("note"));
+        let result = Document::new()
+            .add(IR::from(text))
+            .add(IR::from(note));
+        assert_eq!(convert_file(content), result);
     }
 
     #[test]
@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use super::html::Nodes;
 use super::html::Node;
 
@@ -9,27 +11,62 @@ pub fn remove_tags(node: Node) -> Nodes {
     }
     match node {
         Node::Text(x) => Nodes::from(Node::Text(x)),
-        Node::Element { tag, attributes, children } => {
+        Node::Element { tag, attributes, children, line } => {
             let children = handle_children(children);
 
             match tag.as_str() {
                 // Contents are not useful
                 "head" | "button" | "noscript" | "form" | "script" | "style" => Nodes::new(),
                 // Contents are useful
-                "a" | "i" | "em" | "strong" | "mark" | "b" | "span" 
-                    | "cite" | "q" => children,
+                "a" | "mark" | "span" | "cite" | "q" | "time" | "data" | "bdi" | "bdo" | "u" |
+                "font" => children,
+                // Emphasis is kept so terms can be turned into clozes
+                "b" | "strong" => {
+                    Nodes::from(Node::Element {
+                        tag: "strong".to_string(),
+                        attributes: vec![],
+                        children,
+                        line,
+                    })
+                }
+                "i" | "em" => {
+                    Nodes::from(Node::Element {
+                        tag: "em".to_string(),
+                        attributes: vec![],
+                        children,
+                        line,
+                    })
+                }
+                "figure" => figure(children, line),
                 "dl" => {
                     Nodes::from(Node::Element {
                         tag: "ul".to_string(),
                         attributes: vec![],
                         children,
+                        line,
+                    })
+                }
+                "dt" => {
+                    let term = Node::Element {
+                        tag: "dfn".to_string(),
+                        attributes: vec![],
+                        children,
+                        line,
+                    };
+
+                    Nodes::from(Node::Element {
+                        tag: "li".to_string(),
+                        attributes: vec![],
+                        children: Nodes::from(term),
+                        line,
                     })
                 }
-                "dd" | "dt" => {
+                "dd" => {
                     Nodes::from(Node::Element {
                         tag: "li".to_string(),
                         attributes: vec![],
                         children,
+                        line,
                     })
                 }
                 // Contents are useful but tags are containers
@@ -40,6 +77,7 @@ pub fn remove_tags(node: Node) -> Nodes {
                         tag: tag,
                         attributes: vec![],
                         children: children,
+                        line,
                     })
                 }
                 _ => {
@@ -47,6 +85,7 @@ pub fn remove_tags(node: Node) -> Nodes {
                         tag: tag,
                         attributes: attributes,
                         children: children,
+                        line,
                     })
                 }
             }
@@ -54,6 +93,282 @@ pub fn remove_tags(node: Node) -> Nodes {
     }
 }
 
+/// Keeps the content of a figure where it is. The image of a figure with a
+/// single image and a caption is put in a `figure` of its own along with the
+/// caption, so the caption can be given to the image, and any other figure is
+/// only a container.
+fn figure(children: Nodes, line: Option<usize>) -> Nodes {
+    let images = children.iter()
+        .map(|child| child.find("img").len())
+        .sum::<usize>();
+    let caption = children.iter()
+        .flat_map(|child| child.find("figcaption"))
+        .next()
+        .cloned();
+
+    let children = match caption {
+        Some(ref caption) if images == 1 => with_caption(children, caption),
+        _ => children,
+    };
+
+    Nodes::from(Node::Element {
+        tag: "div".to_string(),
+        attributes: vec![],
+        children,
+        line,
+    })
+}
+
+/// Replaces the image with a `figure` holding the image and the caption, and
+/// removes the caption from where it was.
+fn with_caption(nodes: Nodes, caption: &Node) -> Nodes {
+    nodes.into_iter()
+        .flat_map(|node| match node {
+            Node::Element { ref tag, .. } if tag == "figcaption" => Nodes::new(),
+            Node::Element { ref tag, line, .. } if tag == "img" => {
+                Nodes::from(Node::Element {
+                    tag: "figure".to_string(),
+                    attributes: vec![],
+                    children: Nodes::from(node).add(caption.clone()),
+                    line,
+                })
+            }
+            Node::Element { tag, attributes, children, line } => {
+                Nodes::from(Node::Element {
+                    tag,
+                    attributes,
+                    children: with_caption(children, caption),
+                    line,
+                })
+            }
+            node => Nodes::from(node),
+        })
+        .collect()
+}
+
+fn attribute<'a>(node: &'a Node, name: &str) -> Option<&'a str> {
+    match *node {
+        Node::Element { ref attributes, .. } => {
+            attributes.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        }
+        Node::Text(_) => None,
+    }
+}
+
+fn is_tag(node: &Node, name: &str) -> bool {
+    match *node {
+        Node::Element { ref tag, .. } => tag == name,
+        Node::Text(_) => false,
+    }
+}
+
+fn is_blank(node: &Node) -> bool {
+    match *node {
+        Node::Text(ref x) => x.trim().is_empty(),
+        Node::Element { .. } => false,
+    }
+}
+
+/// The id of the footnote a link points to, for links marked as note
+/// references or wrapped in a `sup` on their own.
+fn footnote_target(node: &Node) -> Option<(String, &Node)> {
+    let link = if is_tag(node, "sup") {
+        match *node {
+            Node::Element { ref children, .. } => {
+                let mut children = children.iter().filter(|child| !is_blank(child));
+                match (children.next(), children.next()) {
+                    (Some(link), None) if is_tag(link, "a") => link,
+                    _ => return None,
+                }
+            }
+            Node::Text(_) => return None,
+        }
+    } else if is_tag(node, "a") &&
+              (attribute(node, "epub:type") == Some("noteref") ||
+               attribute(node, "role") == Some("doc-noteref")) {
+        node
+    } else {
+        return None;
+    };
+
+    let href = attribute(link, "href")?;
+    let hash = href.find('#')?;
+
+    Some((href[hash + 1..].to_string(), link))
+}
+
+fn is_footnote_type(node: &Node) -> bool {
+    let epub_type = attribute(node, "epub:type").unwrap_or("");
+    let role = attribute(node, "role").unwrap_or("");
+
+    epub_type.split_whitespace()
+        .any(|x| x == "footnote" || x == "endnote" || x == "rearnote") ||
+    role == "doc-footnote" || role == "doc-endnote"
+}
+
+fn has_sections(node: &Node) -> bool {
+    match *node {
+        Node::Element { ref tag, ref children, .. } => {
+            matches!(tag.as_str(),
+                     "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "section" | "article") ||
+            children.iter().any(has_sections)
+        }
+        Node::Text(_) => false,
+    }
+}
+
+/// Whether a reference to the element can make it a footnote. Other targets,
+/// like headings or whole sections, are only linked to.
+fn can_be_footnote(node: &Node) -> bool {
+    is_footnote_type(node) ||
+    (["li", "aside", "p", "div"].iter().any(|tag| is_tag(node, tag)) && !has_sections(node))
+}
+
+/// The labels of every footnote referenced in the document, keyed by the id of
+/// the footnote, and the ids of the references, with the footnotes they point
+/// to, so links back to them can be removed.
+#[derive(Default)]
+struct Footnotes {
+    labels: HashMap<String, String>,
+    backlinks: Vec<(String, String)>,
+}
+
+impl Footnotes {
+    fn collect(&mut self, node: &Node) {
+        if let Some((target, link)) = footnote_target(node) {
+            let label = node.clone().into_text().trim().to_string();
+            let ids = attribute(node, "id").into_iter().chain(attribute(link, "id"));
+            self.backlinks.extend(ids.map(|id| (target.clone(), id.to_string())));
+            self.labels.entry(target).or_insert(label);
+            return;
+        }
+
+        if let Node::Element { ref children, .. } = *node {
+            for child in children.iter() {
+                self.collect(child);
+            }
+        }
+    }
+
+    /// The ids of the referenced elements that cannot be footnotes.
+    fn other_targets(&self, node: &Node, found: &mut HashSet<String>) {
+        if let Node::Element { ref children, .. } = *node {
+            match attribute(node, "id") {
+                Some(id) if self.labels.contains_key(id) && !can_be_footnote(node) => {
+                    found.insert(id.to_string());
+                }
+                _ => (),
+            }
+
+            for child in children.iter() {
+                self.other_targets(child, found);
+            }
+        }
+    }
+
+    /// Forgets the references to elements that are not footnotes, so they stay
+    /// as they are. References to elements of other files are kept.
+    fn remove(&mut self, targets: &HashSet<String>) {
+        self.labels.retain(|id, _| !targets.contains(id));
+        self.backlinks.retain(|(target, _)| !targets.contains(target));
+    }
+
+    fn is_backlink(&self, node: &Node) -> bool {
+        if !is_tag(node, "a") {
+            return false;
+        }
+
+        let href = attribute(node, "href").unwrap_or("");
+        match href.find('#') {
+            Some(hash) => self.backlinks.iter().any(|(_, id)| *id == href[hash + 1..]),
+            None => false,
+        }
+    }
+
+    fn rewrite(&self, node: Node, in_footnote: bool) -> Nodes {
+        let target = footnote_target(&node)
+            .map(|(target, _)| target)
+            .filter(|target| self.labels.contains_key(target));
+        if let Some(target) = target {
+            let line = node.line();
+            let label = node.into_text().trim().to_string();
+
+            return Nodes::from(Node::Element {
+                tag: "noteref".to_string(),
+                attributes: vec![("href".to_string(), target)],
+                children: Nodes::from(Node::Text(label)),
+                line,
+            });
+        }
+
+        let id = attribute(&node, "id").map(|id| id.to_string());
+        let is_footnote = is_footnote_type(&node) ||
+                          id.as_ref().is_some_and(|id| self.labels.contains_key(id));
+
+        match node {
+            Node::Text(x) => Nodes::from(Node::Text(x)),
+            Node::Element { tag, attributes, children, line } => {
+                let in_footnote = in_footnote || is_footnote;
+                let children = children.into_iter()
+                    .filter(|child| !(in_footnote && self.is_backlink(child)))
+                    .flat_map(|child| self.rewrite(child, in_footnote))
+                    .collect::<Nodes>();
+
+                if is_footnote {
+                    let id = id.unwrap_or_default();
+                    let label = self.labels.get(&id).cloned().unwrap_or_else(|| id.clone());
+
+                    return Nodes::from(Node::Element {
+                        tag: "footnote".to_string(),
+                        attributes: vec![("id".to_string(), id), ("label".to_string(), label)],
+                        children,
+                        line,
+                    });
+                }
+
+                // Lists and sections that only hold footnotes are unwrapped.
+                let only_footnotes = children.iter().any(|child| is_tag(child, "footnote")) &&
+                                     children.iter().all(|child| {
+                    is_tag(child, "footnote") || is_tag(child, "hr") || is_blank(child)
+                });
+                if only_footnotes {
+                    return children.into_iter()
+                        .filter(|child| is_tag(child, "footnote"))
+                        .collect();
+                }
+
+                Nodes::from(Node::Element {
+                    tag,
+                    attributes,
+                    children,
+                    line,
+                })
+            }
+        }
+    }
+}
+
+/// Links footnote references to the footnotes they refer to. References become
+/// `noteref` elements and the footnotes become `footnote` elements, with the
+/// label of their first reference.
+pub fn footnotes(nodes: Nodes) -> Nodes {
+    let mut footnotes = Footnotes::default();
+    for node in nodes.iter() {
+        footnotes.collect(node);
+    }
+    let mut targets = HashSet::new();
+    for node in nodes.iter() {
+        footnotes.other_targets(node, &mut targets);
+    }
+    footnotes.remove(&targets);
+
+    nodes.into_iter()
+        .flat_map(|node| footnotes.rewrite(node, false))
+        .collect()
+}
+
 pub fn handle_containers(node: Node) -> Nodes {
     fn handle_children(children: Nodes) -> Nodes {
         children.into_iter()
@@ -62,7 +377,7 @@ pub fn handle_containers(node: Node) -> Nodes {
     }
     match node {
         Node::Text(x) => Nodes::from(Node::Text(x)),
-        Node::Element { tag, attributes, children } => {
+        Node::Element { tag, attributes, children, line } => {
             let children = handle_children(children);
 
             match tag.as_str() {
@@ -72,6 +387,7 @@ pub fn handle_containers(node: Node) -> Nodes {
                         tag: tag,
                         attributes: attributes,
                         children: children,
+                        line,
                     })
                 }
             }
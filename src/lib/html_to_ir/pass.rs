@@ -0,0 +1,484 @@
+use std::sync::Arc;
+
+use super::html::Node;
+use super::html::Nodes;
+use super::optimize;
+use super::selector::Selector;
+use super::text;
+
+/// A transformation of the HTML before it is converted into the IR.
+pub trait Pass: Send + Sync {
+    /// The name the pass is known by in a pipeline.
+    fn name(&self) -> &str;
+
+    fn run(&self, nodes: Nodes) -> Nodes;
+}
+
+/// Links footnote references to their footnotes.
+pub struct Footnotes;
+
+impl Pass for Footnotes {
+    fn name(&self) -> &str {
+        "footnotes"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        optimize::footnotes(nodes)
+    }
+}
+
+/// Removes the tags that don't carry content and simplifies the rest.
+pub struct RemoveTags;
+
+impl Pass for RemoveTags {
+    fn name(&self) -> &str {
+        "remove_tags"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        nodes.into_iter()
+            .flat_map(optimize::remove_tags)
+            .collect()
+    }
+}
+
+/// Collapses whitespace as a browser would, outside of `pre`.
+pub struct Whitespace;
+
+impl Pass for Whitespace {
+    fn name(&self) -> &str {
+        "whitespace"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        text::collapse_whitespace(nodes)
+    }
+}
+
+/// Rewrites the text in Unicode Normalization Form C, code included.
+pub struct Nfc;
+
+impl Pass for Nfc {
+    fn name(&self) -> &str {
+        "nfc"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        text::map_text(nodes, true, &text::nfc)
+    }
+}
+
+/// Straightens typographic quotes outside of code.
+pub struct Quotes;
+
+impl Pass for Quotes {
+    fn name(&self) -> &str {
+        "quotes"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        text::map_text(nodes, false, &text::quotes)
+    }
+}
+
+/// Turns the look-alike hyphens and dashes into the common ones outside of
+/// code.
+pub struct Dashes;
+
+impl Pass for Dashes {
+    fn name(&self) -> &str {
+        "dashes"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        text::map_text(nodes, false, &text::dashes)
+    }
+}
+
+/// Replaces containers with their children.
+pub struct HandleContainers;
+
+impl Pass for HandleContainers {
+    fn name(&self) -> &str {
+        "handle_containers"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        nodes.into_iter()
+            .flat_map(optimize::handle_containers)
+            .collect()
+    }
+}
+
+/// Applies a function to every node, from the leaves up.
+fn map_elements<F>(nodes: Nodes, f: &F) -> Nodes
+    where F: Fn(Node) -> Nodes
+{
+    nodes.into_iter()
+        .flat_map(|node| match node {
+            Node::Text(x) => Nodes::from(Node::Text(x)),
+            Node::Element { tag, attributes, children, line } => {
+                f(Node::Element {
+                    tag,
+                    attributes,
+                    children: map_elements(children, f),
+                    line,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Drops the elements with a class, along with their contents.
+pub struct DropClass {
+    class: String,
+}
+
+impl DropClass {
+    pub fn new(class: &str) -> Self {
+        DropClass { class: class.to_string() }
+    }
+}
+
+impl Pass for DropClass {
+    fn name(&self) -> &str {
+        "drop_class"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        map_elements(nodes, &|node| {
+            let dropped = node.attribute("class")
+                .is_some_and(|classes| classes.split_whitespace().any(|x| x == self.class));
+
+            if dropped {
+                Nodes::new()
+            } else {
+                Nodes::from(node)
+            }
+        })
+    }
+}
+
+/// Replaces the elements with a tag by their contents.
+pub struct Unwrap {
+    tag: String,
+}
+
+impl Unwrap {
+    pub fn new(tag: &str) -> Self {
+        Unwrap { tag: tag.to_string() }
+    }
+}
+
+impl Pass for Unwrap {
+    fn name(&self) -> &str {
+        "unwrap"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        map_elements(nodes, &|node| match node {
+            Node::Element { ref tag, .. } if *tag == self.tag => node.children(),
+            node => Nodes::from(node),
+        })
+    }
+}
+
+/// The element without its children, to stand for it among the ancestors of
+/// its descendants.
+fn shallow(tag: &str, attributes: &[(String, String)]) -> Node {
+    Node::Element {
+        tag: tag.to_string(),
+        attributes: attributes.to_vec(),
+        children: Nodes::new(),
+        line: None,
+    }
+}
+
+/// Drops the elements matching a selector, along with their contents.
+pub struct DropMatching {
+    selector: Selector,
+}
+
+impl DropMatching {
+    pub fn new(selector: Selector) -> Self {
+        DropMatching { selector }
+    }
+
+    fn drop(&self, nodes: Nodes, ancestors: &mut Vec<Node>) -> Nodes {
+        let mut kept = Nodes::new();
+        for node in nodes {
+            if self.selector.matches(ancestors, &node) {
+                continue;
+            }
+
+            kept = kept.add(match node {
+                Node::Text(x) => Node::Text(x),
+                Node::Element { tag, attributes, children, line } => {
+                    ancestors.push(shallow(&tag, &attributes));
+                    let children = self.drop(children, ancestors);
+                    ancestors.pop();
+
+                    Node::Element {
+                        tag,
+                        attributes,
+                        children,
+                        line,
+                    }
+                }
+            });
+        }
+
+        kept
+    }
+}
+
+impl Pass for DropMatching {
+    fn name(&self) -> &str {
+        "drop"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        self.drop(nodes, &mut vec![])
+    }
+}
+
+/// Keeps only the elements matching a selector, along with their contents.
+pub struct KeepMatching {
+    selector: Selector,
+}
+
+impl KeepMatching {
+    pub fn new(selector: Selector) -> Self {
+        KeepMatching { selector }
+    }
+
+    fn keep(&self, nodes: Nodes, ancestors: &mut Vec<Node>, kept: &mut Vec<Node>) {
+        for node in nodes {
+            if self.selector.matches(ancestors, &node) {
+                kept.push(node);
+                continue;
+            }
+
+            if let Node::Element { tag, attributes, children, .. } = node {
+                ancestors.push(shallow(&tag, &attributes));
+                self.keep(children, ancestors, kept);
+                ancestors.pop();
+            }
+        }
+    }
+}
+
+impl Pass for KeepMatching {
+    fn name(&self) -> &str {
+        "keep"
+    }
+
+    fn run(&self, nodes: Nodes) -> Nodes {
+        let mut kept = vec![];
+        self.keep(nodes, &mut vec![], &mut kept);
+        if kept.is_empty() {
+            warn!("No elements matched the selector to keep.");
+        }
+
+        kept.into_iter().collect()
+    }
+}
+
+/// The names of the passes that can be looked up with `builtin`.
+pub const BUILTIN: &[&str] = &["footnotes", "remove_tags", "whitespace", "handle_containers"];
+
+/// The built-in passes that only run when asked for, to normalise the text.
+pub const NORMALIZE: &[&str] = &["nfc", "quotes", "dashes"];
+
+/// The built-in pass with the name.
+pub fn builtin(name: &str) -> Option<Arc<dyn Pass>> {
+    match name {
+        "footnotes" => Some(Arc::new(Footnotes)),
+        "remove_tags" => Some(Arc::new(RemoveTags)),
+        "whitespace" => Some(Arc::new(Whitespace)),
+        "handle_containers" => Some(Arc::new(HandleContainers)),
+        "nfc" => Some(Arc::new(Nfc)),
+        "quotes" => Some(Arc::new(Quotes)),
+        "dashes" => Some(Arc::new(Dashes)),
+        _ => None,
+    }
+}
+
+/// The passes to run, in order.
+#[derive(Clone)]
+pub struct Pipeline {
+    passes: Vec<Arc<dyn Pass>>,
+}
+
+impl Pipeline {
+    /// A pipeline without any passes.
+    pub fn new() -> Self {
+        Pipeline { passes: vec![] }
+    }
+
+    /// A pipeline of built-in passes.
+    pub fn from_names(names: &[&str]) -> Result<Self, String> {
+        let mut pipeline = Pipeline::new();
+        for name in names {
+            let pass = builtin(name).ok_or_else(|| format!("Unknown pass {}", name))?;
+            pipeline.passes.push(pass);
+        }
+
+        Ok(pipeline)
+    }
+
+    pub fn add<P: Pass + 'static>(&mut self, pass: P) -> &mut Self {
+        self.passes.push(Arc::new(pass));
+        self
+    }
+
+    /// Adds the built-in pass with the name at the end.
+    pub fn add_builtin(&mut self, name: &str) -> Result<&mut Self, String> {
+        let pass = builtin(name).ok_or_else(|| format!("Unknown pass {}", name))?;
+        self.passes.push(pass);
+        Ok(self)
+    }
+
+    /// Inserts the pass before the first pass with the name, or at the end if
+    /// there is none.
+    pub fn insert_before<P: Pass + 'static>(&mut self, name: &str, pass: P) -> &mut Self {
+        let index = self.position(name).unwrap_or(self.passes.len());
+        self.passes.insert(index, Arc::new(pass));
+        self
+    }
+
+    /// Inserts the pass after the first pass with the name, or at the end if
+    /// there is none.
+    pub fn insert_after<P: Pass + 'static>(&mut self, name: &str, pass: P) -> &mut Self {
+        let index = self.position(name).map_or(self.passes.len(), |index| index + 1);
+        self.passes.insert(index, Arc::new(pass));
+        self
+    }
+
+    /// Removes every pass with the name.
+    pub fn remove(&mut self, name: &str) -> &mut Self {
+        self.passes.retain(|pass| pass.name() != name);
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name() == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&self, nodes: Nodes) -> Nodes {
+        self.passes.iter().fold(nodes, |nodes, pass| {
+            let nodes = pass.run(nodes);
+            debug!("{}: {:#?}", pass.name(), nodes);
+
+            nodes
+        })
+    }
+}
+
+impl Default for Pipeline {
+    /// The passes every document goes through.
+    fn default() -> Self {
+        Pipeline::from_names(BUILTIN).expect("The built-in passes exist")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::convert_file_with;
+    use super::super::selector::Selector;
+    use ir::*;
+
+    fn body(content: &str) -> String {
+        format!("<html><body>{}</body></html>", content)
+    }
+
+    #[test]
+    fn default_order() {
+        assert_eq!(Pipeline::default().names(),
+                   vec!["footnotes", "remove_tags", "whitespace", "handle_containers"]);
+    }
+
+    #[test]
+    fn unknown_pass() {
+        assert!(Pipeline::from_names(&["footnotes", "nope"]).is_err());
+    }
+
+    #[test]
+    fn insert() {
+        let pipeline = Pipeline::default()
+            .insert_before("remove_tags", DropClass::new("ad"))
+            .insert_after("handle_containers", Unwrap::new("aside"))
+            .remove("footnotes")
+            .build();
+
+        assert_eq!(pipeline.names(),
+                   vec!["drop_class", "remove_tags", "whitespace", "handle_containers", "unwrap"]);
+    }
+
+    #[test]
+    fn drop_class() {
+        let content = body("<p>a</p><div class=\"x ad\"><p>b</p></div><p class=\"adx\">c</p>");
+        let pipeline = Pipeline::default()
+            .insert_before("remove_tags", DropClass::new("ad"))
+            .build();
+        let result = Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .add(IR::from(TextBlock::from("c")));
+
+        assert_eq!(convert_file_with(&content, &pipeline), result);
+    }
+
+    #[test]
+    fn keep_and_drop() {
+        let content = body("<nav><p>menu</p></nav>\
+                            <div id=\"main\"><p>a</p><div class=\"ad-slot\"><p>ad</p></div>\
+                            <div><p>b</p></div></div>\
+                            <div id=\"main\"><p class=\"share\">c</p></div>");
+        let keep = Selector::parse("#main").unwrap();
+        let drop = Selector::parse(".ad-slot, #main > p.share").unwrap();
+        let pipeline = Pipeline::default()
+            .insert_before("footnotes", KeepMatching::new(keep))
+            .insert_before("footnotes", DropMatching::new(drop))
+            .build();
+        let result = Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .add(IR::from(TextBlock::from("b")));
+
+        assert_eq!(convert_file_with(&content, &pipeline), result);
+    }
+
+    #[test]
+    fn unwrap() {
+        let content = body("<note><p>a</p></note>");
+        let pipeline = Pipeline::default()
+            .insert_before("remove_tags", Unwrap::new("note"))
+            .build();
+        let result = Document::new().add(IR::from(TextBlock::from("a")));
+
+        assert_eq!(convert_file_with(&content, &pipeline), result);
+    }
+
+    #[test]
+    fn normalize() {
+        let content = body("<p>\u{201c}Cafe\u{301}\u{201d} \u{2010} <code>\u{2018}x\u{2019}</code></p>");
+        let mut pipeline = Pipeline::default();
+        for name in NORMALIZE {
+            pipeline.add_builtin(name).unwrap();
+        }
+        let text = TextBlock::new()
+            .add(Text::text("\"Caf\u{e9}\" - "))
+            .add(Text::Code("\u{2018}x\u{2019}".to_string()))
+            .build();
+
+        assert_eq!(convert_file_with(&content, &pipeline), Document::new().add(IR::from(text)));
+    }
+}
@@ -0,0 +1,424 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::html::Node;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Operator {
+    /// `[name=value]`
+    Equals,
+    /// `[name~=value]`, one of the whitespace separated words.
+    Includes,
+    /// `[name|=value]`, the value or the value followed by `-`.
+    DashMatch,
+    /// `[name^=value]`
+    Prefix,
+    /// `[name$=value]`
+    Suffix,
+    /// `[name*=value]`
+    Substring,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Attribute {
+    name: String,
+    value: Option<(Operator, String)>,
+}
+
+impl Attribute {
+    fn matches(&self, node: &Node) -> bool {
+        let actual = match node.attribute(&self.name) {
+            Some(actual) => actual,
+            None => return false,
+        };
+
+        match self.value {
+            None => true,
+            Some((ref operator, ref value)) => {
+                match *operator {
+                    Operator::Equals => actual == value,
+                    Operator::Includes => actual.split_whitespace().any(|x| x == value),
+                    Operator::DashMatch => {
+                        actual == value ||
+                        actual.strip_prefix(value.as_str()).is_some_and(|x| x.starts_with('-'))
+                    }
+                    Operator::Prefix => !value.is_empty() && actual.starts_with(value.as_str()),
+                    Operator::Suffix => !value.is_empty() && actual.ends_with(value.as_str()),
+                    Operator::Substring => !value.is_empty() && actual.contains(value.as_str()),
+                }
+            }
+        }
+    }
+}
+
+/// The conditions on a single element, such as `div.note#intro`.
+#[derive(Debug, PartialEq, Clone, Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<Attribute>,
+}
+
+impl Compound {
+    fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.ids.is_empty() && self.classes.is_empty() &&
+        self.attributes.is_empty()
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        let tag = match *node {
+            Node::Element { ref tag, .. } => tag,
+            Node::Text(_) => return false,
+        };
+
+        let classes = node.attribute("class").unwrap_or("");
+        let id = node.attribute("id");
+
+        self.tag.as_ref().is_none_or(|x| x.eq_ignore_ascii_case(tag)) &&
+        self.ids.iter().all(|x| id == Some(x.as_str())) &&
+        self.classes.iter().all(|x| classes.split_whitespace().any(|class| class == x)) &&
+        self.attributes.iter().all(|x| x.matches(node))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// A sequence of compound selectors, each related to the one before it by a
+/// combinator.
+#[derive(Debug, PartialEq, Clone)]
+struct Complex {
+    first: Compound,
+    rest: Vec<(Combinator, Compound)>,
+}
+
+impl Complex {
+    fn matches(&self, ancestors: &[Node], node: &Node) -> bool {
+        let mut parts = vec![&self.first];
+        parts.extend(self.rest.iter().map(|(_, compound)| compound));
+        let combinators = self.rest.iter().map(|&(combinator, _)| combinator).collect::<Vec<_>>();
+
+        matches_from(&parts, &combinators, ancestors, node)
+    }
+}
+
+/// Matches the last part against the node, then the parts before it against
+/// its ancestors.
+fn matches_from(parts: &[&Compound],
+                combinators: &[Combinator],
+                ancestors: &[Node],
+                node: &Node)
+                -> bool {
+    let (last, parts) = match parts.split_last() {
+        Some(x) => x,
+        None => return true,
+    };
+    if !last.matches(node) {
+        return false;
+    }
+
+    let (combinator, combinators) = match combinators.split_last() {
+        Some(x) => x,
+        None => return true,
+    };
+
+    match *combinator {
+        Combinator::Child => {
+            match ancestors.split_last() {
+                Some((parent, ancestors)) => matches_from(parts, combinators, ancestors, parent),
+                None => false,
+            }
+        }
+        Combinator::Descendant => {
+            (0..ancestors.len()).rev().any(|i| {
+                matches_from(parts, combinators, &ancestors[..i], &ancestors[i])
+            })
+        }
+    }
+}
+
+/// A CSS selector list, supporting type, class, id and attribute selectors
+/// joined by descendant and child combinators.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let mut parser = Parser { chars: selector.chars().peekable() };
+        let alternatives = parser.selector_list()
+            .map_err(|err| format!("Invalid selector `{}`: {}", selector, err))?;
+
+        Ok(Selector { alternatives })
+    }
+
+    /// Whether the node matches, given its ancestors from the root down to its
+    /// parent.
+    pub fn matches(&self, ancestors: &[Node], node: &Node) -> bool {
+        self.alternatives.iter().any(|x| x.matches(ancestors, node))
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+fn is_ident(x: char) -> bool {
+    x.is_alphanumeric() || x == '-' || x == '_' || x == '\\' || !x.is_ascii()
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) -> bool {
+        let mut skipped = false;
+        while self.chars.peek().is_some_and(|x| x.is_whitespace()) {
+            self.chars.next();
+            skipped = true;
+        }
+
+        skipped
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(x) if x == expected => Ok(()),
+            Some(x) => Err(format!("expected `{}` but found `{}`", expected, x)),
+            None => Err(format!("expected `{}`", expected)),
+        }
+    }
+
+    /// A name, where a backslash escapes the character after it, as in
+    /// `epub\:type`.
+    fn ident(&mut self) -> Result<String, String> {
+        let mut ident = String::new();
+        while let Some(&x) = self.chars.peek() {
+            if !is_ident(x) {
+                break;
+            }
+            self.chars.next();
+
+            if x == '\\' {
+                ident.extend(self.chars.next());
+            } else {
+                ident.push(x);
+            }
+        }
+
+        if ident.is_empty() {
+            match self.chars.peek() {
+                Some(x) => Err(format!("expected a name but found `{}`", x)),
+                None => Err("expected a name".to_string()),
+            }
+        } else {
+            Ok(ident)
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some(x) if x == quote => return Ok(string),
+                Some('\\') => string.extend(self.chars.next()),
+                Some(x) => string.push(x),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn attribute(&mut self) -> Result<Attribute, String> {
+        self.skip_whitespace();
+        let name = self.ident()?;
+        self.skip_whitespace();
+
+        let operator = match self.chars.next() {
+            Some(']') => return Ok(Attribute { name, value: None }),
+            Some('=') => Operator::Equals,
+            Some(x) => {
+                let operator = match x {
+                    '~' => Operator::Includes,
+                    '|' => Operator::DashMatch,
+                    '^' => Operator::Prefix,
+                    '$' => Operator::Suffix,
+                    '*' => Operator::Substring,
+                    _ => return Err(format!("unexpected `{}` in attribute selector", x)),
+                };
+                self.expect('=')?;
+                operator
+            }
+            None => return Err("unterminated attribute selector".to_string()),
+        };
+
+        self.skip_whitespace();
+        let value = match self.chars.peek() {
+            Some(&quote) if quote == '"' || quote == '\'' => {
+                self.chars.next();
+                self.string(quote)?
+            }
+            _ => self.ident()?,
+        };
+        self.skip_whitespace();
+        self.expect(']')?;
+
+        Ok(Attribute {
+            name,
+            value: Some((operator, value)),
+        })
+    }
+
+    fn compound(&mut self) -> Result<Compound, String> {
+        let mut compound = Compound::default();
+
+        let universal = match self.chars.peek() {
+            Some(&'*') => {
+                self.chars.next();
+                true
+            }
+            Some(&x) if is_ident(x) => {
+                compound.tag = Some(self.ident()?);
+                false
+            }
+            _ => false,
+        };
+
+        loop {
+            match self.chars.peek() {
+                Some(&'.') => {
+                    self.chars.next();
+                    compound.classes.push(self.ident()?);
+                }
+                Some(&'#') => {
+                    self.chars.next();
+                    compound.ids.push(self.ident()?);
+                }
+                Some(&'[') => {
+                    self.chars.next();
+                    compound.attributes.push(self.attribute()?);
+                }
+                _ => break,
+            }
+        }
+
+        if compound.is_empty() && !universal {
+            return match self.chars.peek() {
+                Some(x) => Err(format!("unexpected `{}`", x)),
+                None => Err("expected a selector".to_string()),
+            };
+        }
+
+        Ok(compound)
+    }
+
+    fn complex(&mut self) -> Result<Complex, String> {
+        self.skip_whitespace();
+        let mut complex = Complex {
+            first: self.compound()?,
+            rest: vec![],
+        };
+
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.chars.peek() {
+                None | Some(&',') => break,
+                Some(&'>') => {
+                    self.chars.next();
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(&x) => return Err(format!("unexpected `{}`", x)),
+            };
+
+            complex.rest.push((combinator, self.compound()?));
+        }
+
+        Ok(complex)
+    }
+
+    fn selector_list(&mut self) -> Result<Vec<Complex>, String> {
+        let mut alternatives = vec![self.complex()?];
+        while self.chars.next() == Some(',') {
+            alternatives.push(self.complex()?);
+        }
+
+        Ok(alternatives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::html::Nodes;
+
+    fn element(tag: &str, attributes: &[(&str, &str)]) -> Node {
+        Node::Element {
+            tag: tag.to_string(),
+            attributes: attributes.iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            children: Nodes::new(),
+            line: None,
+        }
+    }
+
+    fn matches(selector: &str, ancestors: &[Node], node: &Node) -> bool {
+        Selector::parse(selector).unwrap().matches(ancestors, node)
+    }
+
+    #[test]
+    fn compound() {
+        let node = element("div", &[("class", "sidebar left"), ("id", "nav")]);
+
+        assert!(matches("div", &[], &node));
+        assert!(matches("*", &[], &node));
+        assert!(matches(".sidebar", &[], &node));
+        assert!(matches("div.left.sidebar#nav", &[], &node));
+        assert!(!matches("div.right", &[], &node));
+        assert!(!matches("p, #main", &[], &node));
+        assert!(matches("p, #nav", &[], &node));
+        assert!(!matches("*", &[], &Node::Text("a".to_string())));
+    }
+
+    #[test]
+    fn attributes() {
+        let node = element("a", &[("href", "https://example.com/a.html"),
+                                  ("epub:type", "noteref footnote"),
+                                  ("lang", "en-GB")]);
+
+        assert!(matches("[href]", &[], &node));
+        assert!(matches("a[href^='https:']", &[], &node));
+        assert!(matches("a[href$=\".html\"]", &[], &node));
+        assert!(matches("a[href*=example]", &[], &node));
+        assert!(matches("[epub\\:type~=footnote]", &[], &node));
+        assert!(matches("[lang|=en]", &[], &node));
+        assert!(!matches("[lang=en]", &[], &node));
+        assert!(!matches("[title]", &[], &node));
+    }
+
+    #[test]
+    fn combinators() {
+        let ancestors = [element("body", &[]),
+                         element("div", &[("class", "content")]),
+                         element("section", &[])];
+        let node = element("p", &[]);
+
+        assert!(matches(".content p", &ancestors, &node));
+        assert!(matches("body .content > section > p", &ancestors, &node));
+        assert!(matches("section>p", &ancestors, &node));
+        assert!(!matches(".content > p", &ancestors, &node));
+        assert!(!matches("p p", &ancestors, &node));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Selector::parse("").is_err());
+        assert!(Selector::parse("div >").is_err());
+        assert!(Selector::parse("div..a").is_err());
+        assert!(Selector::parse("[href").is_err());
+        assert!(Selector::parse("a:hover").is_err());
+    }
+}
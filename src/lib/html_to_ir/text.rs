@@ -0,0 +1,260 @@
+extern crate unicode_normalization;
+
+use self::unicode_normalization::UnicodeNormalization;
+
+use super::html::Node;
+use super::html::Nodes;
+
+/// The elements laid out as blocks. Whitespace collapses across any other
+/// element as if its text was part of the surrounding run, as browsers lay out
+/// unknown elements inline.
+const BLOCK: &[&str] = &["address", "article", "aside", "blockquote", "body", "caption", "dd",
+                         "details", "dialog", "div", "dl", "dt", "fieldset", "figcaption",
+                         "figure", "footer", "footnote", "form", "h1", "h2", "h3", "h4", "h5",
+                         "h6", "head", "header", "hgroup", "hr", "html", "li", "main", "nav",
+                         "ol", "p", "pre", "section", "summary", "table", "tbody", "td",
+                         "textarea", "tfoot", "th", "thead", "tr", "ul"];
+
+/// The elements whose text is kept as it is, whitespace and all.
+const PRESERVED: &[&str] = &["pre", "textarea"];
+
+fn is_inline(tag: &str) -> bool {
+    !BLOCK.contains(&tag)
+}
+
+/// The whitespace HTML collapses, which does not include the non-breaking or
+/// other Unicode spaces.
+fn is_collapsible(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\u{c}')
+}
+
+/// Characters that never show up in the text, like zero-width spaces and soft
+/// hyphens. Joiners are only dropped next to whitespace or at the edges of the
+/// text, where they don't join anything, so emoji sequences and scripts that
+/// need them are kept.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{ad}' | '\u{200b}' | '\u{2060}' | '\u{feff}')
+}
+
+fn is_joiner(c: char) -> bool {
+    matches!(c, '\u{200c}' | '\u{200d}')
+}
+
+/// Spaces that are written as a plain space, so they collapse like one.
+fn is_space(c: char) -> bool {
+    matches!(c, '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' |
+                '\u{3000}')
+}
+
+/// Collapses whitespace the way a browser lays the text out: runs of
+/// whitespace become one space, even across inline elements, and whitespace at
+/// the start and end of a block is removed. Non-breaking and other Unicode
+/// spaces become plain spaces and invisible characters are removed. Text in
+/// `pre` is left alone.
+pub fn collapse_whitespace(nodes: Nodes) -> Nodes {
+    block(nodes)
+}
+
+fn block(nodes: Nodes) -> Nodes {
+    let mut out = vec![];
+    let mut after_space = true;
+    collapse(nodes, &mut out, &mut after_space);
+    trim_end(&mut out);
+
+    out.into_iter().collect()
+}
+
+/// Collapses the nodes into `out`, with `after_space` telling whether the text
+/// so far ends with a space, or is the start of a block, so the next space is
+/// dropped.
+fn collapse(nodes: Nodes, out: &mut Vec<Node>, after_space: &mut bool) {
+    for node in nodes {
+        match node {
+            Node::Text(x) => out.push(Node::Text(collapse_text(&x, after_space))),
+            Node::Element { tag, attributes, children, line } => {
+                if !is_inline(&tag) {
+                    trim_end(out);
+                    let children = if PRESERVED.contains(&tag.as_str()) {
+                        children
+                    } else {
+                        block(children)
+                    };
+                    out.push(Node::Element { tag, attributes, children, line });
+                    *after_space = true;
+                } else {
+                    let mut inner = vec![];
+                    collapse(children, &mut inner, after_space);
+                    match tag.as_str() {
+                        "br" => *after_space = true,
+                        "img" => *after_space = false,
+                        _ => (),
+                    }
+                    let children = inner.into_iter().collect();
+                    out.push(Node::Element { tag, attributes, children, line });
+                }
+            }
+        }
+    }
+}
+
+fn collapse_text(text: &str, after_space: &mut bool) -> String {
+    let chars = text.chars().filter(|&c| !is_invisible(c)).collect::<Vec<_>>();
+    let is_blank = |index: Option<usize>| {
+        index.and_then(|index| chars.get(index))
+            .is_none_or(|&c| is_collapsible(c) || is_space(c))
+    };
+
+    let mut result = String::with_capacity(text.len());
+    for (index, &c) in chars.iter().enumerate() {
+        if is_joiner(c) && (is_blank(index.checked_sub(1)) || is_blank(Some(index + 1))) {
+            continue;
+        }
+
+        if is_collapsible(c) || is_space(c) {
+            if !*after_space {
+                result.push(' ');
+                *after_space = true;
+            }
+        } else {
+            result.push(c);
+            *after_space = false;
+        }
+    }
+
+    result
+}
+
+/// Removes the space at the end of the inline text, looking into the inline
+/// elements it ends with. Returns whether there was any text to trim.
+fn trim_end(nodes: &mut [Node]) -> bool {
+    for node in nodes.iter_mut().rev() {
+        match *node {
+            Node::Text(ref mut x) => {
+                let len = x.trim_end_matches(' ').len();
+                x.truncate(len);
+                if !x.is_empty() {
+                    return true;
+                }
+            }
+            Node::Element { ref tag, ref mut children, .. } => {
+                if !is_inline(tag) || tag == "img" || tag == "br" {
+                    return true;
+                }
+
+                let mut inner = children.clone().into_iter().collect::<Vec<_>>();
+                let found = trim_end(&mut inner);
+                *children = inner.into_iter().collect();
+                if found {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Applies the function to the text of every element, except for the
+/// preformatted and code ones unless `code` is set.
+pub fn map_text<F>(nodes: Nodes, code: bool, f: &F) -> Nodes
+    where F: Fn(&str) -> String
+{
+    nodes.into_iter()
+        .map(|node| match node {
+            Node::Text(x) => Node::Text(f(&x)),
+            Node::Element { tag, attributes, children, line } => {
+                let skip = !code && (PRESERVED.contains(&tag.as_str()) || tag == "code");
+                let children = if skip { children } else { map_text(children, code, f) };
+
+                Node::Element { tag, attributes, children, line }
+            }
+        })
+        .collect()
+}
+
+/// The text in Unicode Normalization Form C, so the same letter is always
+/// written with the same characters.
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// Curly and low quotes as the ASCII `'` and `"`.
+pub fn quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' => '\'',
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' => '"',
+            c => c,
+        })
+        .collect()
+}
+
+/// Hyphens and the minus sign as the ASCII `-`, and the other dashes as en and
+/// em dashes.
+pub fn dashes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2010}' | '\u{2011}' | '\u{2043}' | '\u{2212}' | '\u{fe63}' | '\u{ff0d}' => '-',
+            '\u{2012}' => '\u{2013}',
+            '\u{2015}' | '\u{fe58}' => '\u{2014}',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(x: &str) -> Node {
+        Node::Text(x.to_string())
+    }
+
+    fn element(tag: &str, children: Vec<Node>) -> Node {
+        Node::Element {
+            tag: tag.to_string(),
+            attributes: vec![],
+            children: children.into_iter().collect(),
+            line: None,
+        }
+    }
+
+    fn texts(nodes: &Nodes) -> Vec<String> {
+        nodes.iter()
+            .map(|node| match *node {
+                Node::Text(ref x) => x.clone(),
+                Node::Element { ref tag, ref children, .. } => {
+                    format!("<{}>{}", tag, texts(children).join("|"))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn whitespace() {
+        let nodes = vec![text("\n  "),
+                         element("p",
+                                 vec![text("  a\n\n b\u{a0} "),
+                                      element("strong", vec![text(" c ")]),
+                                      text(" d\u{200b}e \t")]),
+                         text("\n"),
+                         element("pre", vec![text(" x\n  y ")])];
+        let nodes = collapse_whitespace(nodes.into_iter().collect());
+
+        assert_eq!(texts(&nodes), vec!["<p>a b |<strong>c |de", "<pre> x\n  y "]);
+    }
+
+    #[test]
+    fn joiners() {
+        let mut after_space = true;
+        assert_eq!(collapse_text("\u{200d}a \u{200d}b\u{200c}c\u{200d}", &mut after_space),
+                   "a b\u{200c}c");
+    }
+
+    #[test]
+    fn typography() {
+        assert_eq!(quotes("\u{201c}It\u{2019}s\u{201d}"), "\"It's\"");
+        assert_eq!(dashes("a\u{2010}b \u{2015} 1\u{2012}2"), "a-b \u{2014} 1\u{2013}2");
+        assert_eq!(nfc("e\u{301}"), "\u{e9}");
+    }
+}
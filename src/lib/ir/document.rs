@@ -1,22 +1,94 @@
 use ir::IR;
+use ir::Metadata;
+use ir::Origin;
+use ir::Section;
 
 use std::iter::FromIterator;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Document {
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    metadata: Metadata,
     children: Vec<IR>,
+    /// The origin of each child, where it is known. It can be shorter than the
+    /// children when the last ones have none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    origins: Vec<Option<Origin>>,
 }
 
 impl Document {
     pub fn new() -> Self {
-        Document { children: vec![] }
+        Document {
+            metadata: Metadata::new(),
+            children: vec![],
+            origins: vec![],
+        }
     }
 
     pub fn add(self, item: IR) -> Self {
         let mut children = self.children;
         children.push(item);
 
-        Document { children: children }
+        Document {
+            metadata: self.metadata,
+            children: children,
+            origins: self.origins,
+        }
+    }
+
+    /// Adds a child along with where it came from in the input.
+    pub fn add_from(self, item: IR, origin: Option<Origin>) -> Self {
+        let mut origins = self.origins;
+        if origin.is_some() {
+            origins.resize(self.children.len(), None);
+            origins.push(origin);
+        }
+
+        Document { origins, ..self }.add(item)
+    }
+
+    pub fn set_metadata(self, metadata: Metadata) -> Self {
+        Document { metadata, ..self }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Replaces the origins of the children, such as those of the document a
+    /// rewritten one was made from.
+    pub fn set_origins(self, origins: Vec<Option<Origin>>) -> Self {
+        Document { origins, ..self }
+    }
+
+    pub fn origins(&self) -> &[Option<Origin>] {
+        &self.origins
+    }
+
+    /// Where the child at the index came from, if it is known.
+    pub fn origin(&self, index: usize) -> Option<&Origin> {
+        self.origins.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, IR> {
+        self.children.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// The children nested under their headings.
+    pub fn sections(&self) -> Section {
+        Section::tree(self.children.iter().cloned())
+    }
+}
+
+/// Origins only help to trace the conversion, so documents with the same
+/// content are equal wherever it came from.
+impl PartialEq for Document {
+    fn eq(&self, other: &Document) -> bool {
+        self.metadata == other.metadata && self.children == other.children
     }
 }
 
@@ -0,0 +1,61 @@
+use ir::TextBlock;
+
+/// A reference in the text to the footnote with the given id.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FootnoteRef {
+    id: String,
+    label: String,
+}
+
+impl FootnoteRef {
+    pub fn new(id: &str, label: &str) -> Self {
+        FootnoteRef {
+            id: id.to_string(),
+            label: label.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The marker shown in the text, such as "1" or "*".
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// The text of a footnote or endnote. Its label is the label of the first
+/// reference to it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Footnote {
+    id: String,
+    label: String,
+    content: TextBlock,
+}
+
+impl Footnote {
+    pub fn new(id: &str, label: &str, content: TextBlock) -> Self {
+        Footnote {
+            id: id.to_string(),
+            label: label.to_string(),
+            content,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn content_ref(&self) -> &TextBlock {
+        &self.content
+    }
+
+    pub fn content(self) -> TextBlock {
+        self.content
+    }
+}
@@ -0,0 +1,86 @@
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Image {
+    src: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    caption: Option<String>,
+}
+
+impl Image {
+    pub fn new(src: &str) -> Self {
+        Image {
+            src: src.to_string(),
+            alt: None,
+            title: None,
+            width: None,
+            height: None,
+            caption: None,
+        }
+    }
+
+    pub fn set_src(&mut self, src: &str) -> &mut Self {
+        self.src = src.to_string();
+        self
+    }
+
+    pub fn set_alt(&mut self, alt: &str) -> &mut Self {
+        self.alt = Some(alt.to_string());
+        self
+    }
+
+    pub fn set_title(&mut self, title: &str) -> &mut Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn set_width(&mut self, width: &str) -> &mut Self {
+        self.width = Some(width.to_string());
+        self
+    }
+
+    pub fn set_height(&mut self, height: &str) -> &mut Self {
+        self.height = Some(height.to_string());
+        self
+    }
+
+    /// The caption of the `<figure>` the image is in.
+    pub fn set_caption(&mut self, caption: &str) -> &mut Self {
+        self.caption = Some(caption.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn alt(&self) -> Option<&str> {
+        self.alt.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn width(&self) -> Option<&str> {
+        self.width.as_deref()
+    }
+
+    pub fn height(&self) -> Option<&str> {
+        self.height.as_deref()
+    }
+
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_deref()
+    }
+}
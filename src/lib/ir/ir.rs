@@ -1,20 +1,25 @@
 use ir::TextBlock;
 use ir::List;
 use ir::Table;
+use ir::Image;
+use ir::Footnote;
+use ir::ListContent;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum IR {
-    Img(String),
+    Img(Image),
     Pre(String),
     Par(TextBlock),
     List(List),
     Table(Table),
     Header(usize, String),
+    Footnote(Footnote),
 }
 
 impl IR {
     pub fn img(src: &str) -> Self {
-        IR::Img(src.to_string())
+        IR::Img(Image::new(src))
     }
 
     pub fn pre(text: &str) -> Self {
@@ -24,6 +29,43 @@ impl IR {
     pub fn header(level: usize, text: &str) -> Self {
         IR::Header(level, text.to_string())
     }
+
+    /// The blocks in the items of a list or the cells of a table, in order,
+    /// without the ones nested further inside them.
+    pub fn nested(&self) -> Vec<&IR> {
+        fn list<'a>(items: &'a List, blocks: &mut Vec<&'a IR>) {
+            for content in items.iter().flat_map(|item| item.iter()) {
+                match *content {
+                    ListContent::Block(ref block) => blocks.push(block),
+                    ListContent::List(ref nested) => list(nested, blocks),
+                    ListContent::Text(_) => (),
+                }
+            }
+        }
+
+        let mut blocks = vec![];
+        match *self {
+            IR::List(ref x) => list(x, &mut blocks),
+            IR::Table(ref table) => {
+                blocks.extend(table.rows().flat_map(|row| row.iter()).flat_map(|cell| cell.iter()))
+            }
+            _ => (),
+        }
+
+        blocks
+    }
+}
+
+impl From<Image> for IR {
+    fn from(image: Image) -> Self {
+        IR::Img(image)
+    }
+}
+
+impl From<Footnote> for IR {
+    fn from(footnote: Footnote) -> Self {
+        IR::Footnote(footnote)
+    }
 }
 
 impl From<TextBlock> for IR {
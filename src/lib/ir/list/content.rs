@@ -1,10 +1,14 @@
 use super::List;
 use ir::TextBlock;
+use ir::IR;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum ListContent {
     Text(TextBlock),
     List(List),
+    /// Any other block, like an image, a table or code.
+    Block(IR),
 }
 
 impl From<TextBlock> for ListContent {
@@ -30,3 +34,9 @@ impl From<List> for ListContent {
         ListContent::List(list)
     }
 }
+
+impl From<IR> for ListContent {
+    fn from(block: IR) -> Self {
+        ListContent::Block(block)
+    }
+}
@@ -2,14 +2,21 @@ use super::ListContent;
 use super::List;
 use ir::TextBlock;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ListItem {
     content: Vec<ListContent>,
+    /// The number of the item in an ordered list, if it does not follow from
+    /// the ones before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<i64>,
 }
 
 impl ListItem {
     pub fn new() -> Self {
-        ListItem { content: vec![] }
+        ListItem {
+            content: vec![],
+            value: None,
+        }
     }
 
     pub fn add(&mut self, content: ListContent) -> &mut Self {
@@ -17,6 +24,19 @@ impl ListItem {
         self
     }
 
+    pub fn set_value(&mut self, value: i64) -> &mut Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn value(&self) -> Option<i64> {
+        self.value
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, ListContent> {
+        self.content.iter()
+    }
+
     pub fn build(&self) -> Self {
         self.clone()
     }
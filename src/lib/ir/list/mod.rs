@@ -3,11 +3,13 @@ pub use self::item::ListItem;
 
 mod style;
 pub use self::style::ListType;
+pub use self::style::Numbering;
+pub use self::style::NumberStyle;
 
 mod content;
 pub use self::content::ListContent;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct List {
     style: ListType,
     items: Vec<ListItem>,
@@ -25,11 +27,39 @@ impl List {
         &self.style
     }
 
+    /// The number of every item. An ordered list counts from its start, down
+    /// if it is reversed, and an item with a value of its own continues the
+    /// count from there.
+    pub fn numbers(&self) -> Vec<i64> {
+        let (start, step) = match self.style {
+            ListType::Ordered(ref numbering) if numbering.reversed() => {
+                (numbering.start().unwrap_or(self.items.len() as i64), -1)
+            }
+            ListType::Ordered(ref numbering) => (numbering.start().unwrap_or(1), 1),
+            ListType::Unordered => (1, 1),
+        };
+
+        let mut next = start;
+        self.items
+            .iter()
+            .map(|item| {
+                let number = item.value().unwrap_or(next);
+                next = number + step;
+
+                number
+            })
+            .collect()
+    }
+
     pub fn add(&mut self, item: ListItem) -> &mut Self {
         self.items.push(item);
         self
     }
 
+    pub fn iter(&self) -> ::std::slice::Iter<'_, ListItem> {
+        self.items.iter()
+    }
+
     pub fn build(&self) -> Self {
         self.clone()
     }
@@ -1,5 +1,145 @@
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ListType {
-    Ordered,
+    Ordered(Numbering),
     Unordered,
 }
+
+impl ListType {
+    /// An ordered list numbered 1, 2, 3 and so on.
+    pub fn ordered() -> Self {
+        ListType::Ordered(Numbering::default())
+    }
+}
+
+/// How the items of an ordered list are numbered.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Numbering {
+    /// The number of the first item. Without it, a list counts up from 1, or
+    /// down from the number of items if it is reversed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<i64>,
+    reversed: bool,
+    style: NumberStyle,
+}
+
+impl Numbering {
+    pub fn new() -> Self {
+        Numbering::default()
+    }
+
+    pub fn set_start(&mut self, start: i64) -> &mut Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn set_reversed(&mut self, reversed: bool) -> &mut Self {
+        self.reversed = reversed;
+        self
+    }
+
+    pub fn set_style(&mut self, style: NumberStyle) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn start(&self) -> Option<i64> {
+        self.start
+    }
+
+    pub fn reversed(&self) -> bool {
+        self.reversed
+    }
+
+    pub fn style(&self) -> &NumberStyle {
+        &self.style
+    }
+}
+
+/// The numerals of an ordered list, as in the `type` of an HTML `ol`.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberStyle {
+    #[default]
+    Decimal,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+}
+
+impl NumberStyle {
+    /// Writes the number in this style. Numbers that letters or roman numerals
+    /// cannot write, like 0 or negative numbers, are written as decimals, as
+    /// browsers do.
+    pub fn format(&self, number: i64) -> String {
+        match *self {
+            NumberStyle::Decimal => number.to_string(),
+            NumberStyle::LowerAlpha => alpha(number).unwrap_or_else(|| number.to_string()),
+            NumberStyle::UpperAlpha => {
+                alpha(number).map_or_else(|| number.to_string(), |x| x.to_uppercase())
+            }
+            NumberStyle::LowerRoman => roman(number).unwrap_or_else(|| number.to_string()),
+            NumberStyle::UpperRoman => {
+                roman(number).map_or_else(|| number.to_string(), |x| x.to_uppercase())
+            }
+        }
+    }
+}
+
+/// a to z, then aa, ab and so on.
+fn alpha(number: i64) -> Option<String> {
+    if number < 1 {
+        return None;
+    }
+
+    let mut letters = vec![];
+    let mut rest = number;
+    while rest > 0 {
+        rest -= 1;
+        letters.push((b'a' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+
+    Some(letters.into_iter().rev().collect())
+}
+
+fn roman(number: i64) -> Option<String> {
+    const NUMERALS: &[(i64, &str)] = &[(1000, "m"), (900, "cm"), (500, "d"), (400, "cd"),
+                                       (100, "c"), (90, "xc"), (50, "l"), (40, "xl"),
+                                       (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")];
+    if !(1..4000).contains(&number) {
+        return None;
+    }
+
+    let mut result = String::new();
+    let mut rest = number;
+    for &(value, numeral) in NUMERALS {
+        while rest >= value {
+            result.push_str(numeral);
+            rest -= value;
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!(NumberStyle::Decimal.format(-2), "-2");
+        assert_eq!(NumberStyle::LowerAlpha.format(28), "ab");
+        assert_eq!(NumberStyle::UpperAlpha.format(26), "Z");
+        assert_eq!(NumberStyle::LowerRoman.format(4), "iv");
+        assert_eq!(NumberStyle::UpperRoman.format(1994), "MCMXCIV");
+        assert_eq!(NumberStyle::LowerRoman.format(0), "0");
+    }
+}
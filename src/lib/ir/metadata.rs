@@ -0,0 +1,155 @@
+/// What is known about where a document came from.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    /// The publication date, as given by the document.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    /// The canonical URL of the document.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_name: Option<String>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Metadata::default()
+    }
+
+    pub fn set_title(&mut self, title: &str) -> &mut Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn set_author(&mut self, author: &str) -> &mut Self {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    pub fn set_published(&mut self, published: &str) -> &mut Self {
+        self.published = Some(published.to_string());
+        self
+    }
+
+    pub fn set_url(&mut self, url: &str) -> &mut Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    pub fn set_language(&mut self, language: &str) -> &mut Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    pub fn set_description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn set_site_name(&mut self, site_name: &str) -> &mut Self {
+        self.site_name = Some(site_name.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn published(&self) -> Option<&str> {
+        self.published.as_deref()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn site_name(&self) -> Option<&str> {
+        self.site_name.as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// The line crediting the source, such as "Cell, Jane Doe, Wikipedia,
+    /// 2020-01-02", or `None` if nothing is known about it.
+    pub fn source(&self) -> Option<String> {
+        let date = self.published.as_ref().map(|x| x.get(..10).unwrap_or(x).to_string());
+        let parts = [self.title.clone(), self.author.clone(), self.site_name.clone(), date]
+            .iter()
+            .flatten()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            self.url.clone()
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+
+    /// Tags for the cards of the document: the site it is from and its
+    /// language.
+    pub fn tags(&self) -> Vec<String> {
+        let host = self.url.as_ref().and_then(|url| {
+            let rest = &url[url.find("://")? + 3..];
+            let end = rest.find(['/', ':', '?', '#']).unwrap_or(rest.len());
+            let host = rest[..end].to_lowercase();
+
+            Some(host.trim_start_matches("www.").to_string())
+        });
+        let language = self.language.as_ref().map(|x| format!("lang:{}", x.to_lowercase()));
+
+        host.into_iter()
+            .filter(|x| !x.is_empty())
+            .chain(language)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source() {
+        let metadata = Metadata::new()
+            .set_title("Cell")
+            .set_author("Jane Doe")
+            .set_published("2020-01-02T10:00:00Z")
+            .set_url("https://www.Example.com/cell")
+            .set_language("en-GB")
+            .build();
+
+        assert_eq!(metadata.source(), Some("Cell, Jane Doe, 2020-01-02".to_string()));
+        assert_eq!(metadata.tags(), vec!["example.com", "lang:en-gb"]);
+        assert_eq!(Metadata::new().source(), None);
+        assert!(Metadata::new().is_empty());
+    }
+}
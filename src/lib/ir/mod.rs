@@ -7,14 +7,37 @@ pub use self::list::List;
 pub use self::list::ListItem;
 pub use self::list::ListType;
 pub use self::list::ListContent;
+pub use self::list::Numbering;
+pub use self::list::NumberStyle;
 
 mod table;
 pub use self::table::TableCell;
 pub use self::table::TableRow;
 pub use self::table::Table;
 
+mod footnote;
+pub use self::footnote::Footnote;
+pub use self::footnote::FootnoteRef;
+
+mod image;
+pub use self::image::Image;
+
 mod ir;
 pub use self::ir::IR;
 
+mod metadata;
+pub use self::metadata::Metadata;
+
+mod origin;
+pub use self::origin::Origin;
+
 mod document;
 pub use self::document::Document;
+
+mod section;
+pub use self::section::Section;
+pub use self::section::Sections;
+
+pub mod visit;
+pub use self::visit::Visitor;
+pub use self::visit::Fold;
@@ -0,0 +1,39 @@
+/// Where a block of the document came from in the input: the element it was
+/// converted from and the line that element started on. The HTML parser only
+/// reports lines, not columns, so blocks from elements that start on the same
+/// line, as in minified HTML, are only told apart by their tags.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Origin {
+    tag: String,
+    line: usize,
+    /// The origins of the blocks nested in this one, in the order of
+    /// `IR::nested`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    nested: Vec<Option<Origin>>,
+}
+
+impl Origin {
+    pub fn new(tag: &str, line: usize) -> Self {
+        Origin {
+            tag: tag.to_string(),
+            line,
+            nested: vec![],
+        }
+    }
+
+    pub fn set_nested(self, nested: Vec<Option<Origin>>) -> Self {
+        Origin { nested, ..self }
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn nested(&self) -> &[Option<Origin>] {
+        &self.nested
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use ir::IR;
+
+/// A heading with the blocks under it and the sections of lower level headings
+/// that follow, as a view of the flat blocks of a document. The root of the
+/// tree has no heading and holds what comes before the first one.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Section {
+    heading: Option<String>,
+    level: usize,
+    /// A unique id for linking to the section, made from its heading.
+    anchor: Option<String>,
+    children: Vec<IR>,
+    subsections: Vec<Section>,
+}
+
+impl Section {
+    fn root() -> Self {
+        Section {
+            heading: None,
+            level: 0,
+            anchor: None,
+            children: vec![],
+            subsections: vec![],
+        }
+    }
+
+    /// Nests the blocks under their headings. A heading ends the sections of
+    /// the same or a lower level before it, so a skipped level, like an `h3`
+    /// right under an `h1`, is a subsection without one in between.
+    pub fn tree<I: IntoIterator<Item = IR>>(blocks: I) -> Self {
+        fn close(stack: &mut Vec<Section>) {
+            let section = stack.pop().expect("The root is never closed");
+            stack.last_mut()
+                .expect("The root is never closed")
+                .subsections
+                .push(section);
+        }
+
+        let mut stack = vec![Section::root()];
+        let mut anchors = HashMap::new();
+        for block in blocks {
+            match block {
+                IR::Header(level, text) => {
+                    while stack.len() > 1 && stack.last().is_some_and(|x| x.level >= level) {
+                        close(&mut stack);
+                    }
+
+                    let anchor = anchor(&text, &mut anchors);
+                    stack.push(Section {
+                        heading: Some(text),
+                        level,
+                        anchor: Some(anchor),
+                        children: vec![],
+                        subsections: vec![],
+                    });
+                }
+                block => {
+                    stack.last_mut()
+                        .expect("There is always a section")
+                        .children
+                        .push(block)
+                }
+            }
+        }
+
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+
+        stack.pop().expect("There is always a section")
+    }
+
+    pub fn heading(&self) -> Option<&str> {
+        self.heading.as_deref()
+    }
+
+    /// The level of the heading, or 0 for the root.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn anchor(&self) -> Option<&str> {
+        self.anchor.as_deref()
+    }
+
+    /// The blocks between the heading and the first subsection.
+    pub fn children(&self) -> &[IR] {
+        &self.children
+    }
+
+    pub fn subsections(&self) -> &[Section] {
+        &self.subsections
+    }
+
+    /// This section and all the ones below it in document order, each with
+    /// the headings from the top of the tree down to its own.
+    pub fn iter(&self) -> Sections<'_> {
+        Sections { stack: vec![(self.heading().into_iter().collect(), self)] }
+    }
+}
+
+/// The sections of a tree in document order, with their heading paths.
+pub struct Sections<'a> {
+    stack: Vec<(Vec<&'a str>, &'a Section)>,
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = (Vec<&'a str>, &'a Section);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, section) = self.stack.pop()?;
+        for subsection in section.subsections.iter().rev() {
+            let mut path = path.clone();
+            path.extend(subsection.heading());
+            self.stack.push((path, subsection));
+        }
+
+        Some((path, section))
+    }
+}
+
+/// The heading in lower case with dashes for spaces and without punctuation,
+/// as most Markdown renderers make them, numbered if it is already taken.
+/// `taken` holds every anchor given out, with how many times it was numbered.
+fn anchor(heading: &str, taken: &mut HashMap<String, usize>) -> String {
+    let slug = heading.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| if c.is_whitespace() {
+            Some('-')
+        } else if c.is_alphanumeric() || c == '-' || c == '_' {
+            Some(c)
+        } else {
+            None
+        })
+        .collect::<String>();
+    let slug = if slug.is_empty() { "section".to_string() } else { slug };
+
+    // The numbered slug can be the anchor of another heading as well, like
+    // "a-1" for a heading that reads "A 1", so it is counted on until free.
+    let mut anchor = slug.clone();
+    while taken.contains_key(&anchor) {
+        let count = taken.get_mut(&slug).expect("The slug is taken");
+        *count += 1;
+        anchor = format!("{}-{}", slug, count);
+    }
+    taken.insert(anchor.clone(), 0);
+
+    anchor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::TextBlock;
+
+    fn par(text: &str) -> IR {
+        IR::from(TextBlock::from(text))
+    }
+
+    #[test]
+    fn tree() {
+        let blocks = vec![par("intro"),
+                          IR::header(1, "A"),
+                          par("a"),
+                          IR::header(3, "Deep"),
+                          par("deep"),
+                          IR::header(2, "B c!"),
+                          IR::header(1, "A")];
+        let tree = Section::tree(blocks);
+
+        assert_eq!(tree.children(), &[par("intro")]);
+        assert_eq!(tree.subsections().len(), 2);
+
+        let sections = tree.iter()
+            .map(|(path, section)| (path.join(" > "), section.level(), section.anchor()))
+            .collect::<Vec<_>>();
+        assert_eq!(sections,
+                   vec![("".to_string(), 0, None),
+                        ("A".to_string(), 1, Some("a")),
+                        ("A > Deep".to_string(), 3, Some("deep")),
+                        ("A > B c!".to_string(), 2, Some("b-c")),
+                        ("A".to_string(), 1, Some("a-1"))]);
+
+        let first = &tree.subsections()[0];
+        assert_eq!(first.children(), &[par("a")]);
+        assert_eq!(first.subsections()[0].children(), &[par("deep")]);
+    }
+
+    #[test]
+    fn unique_anchors() {
+        let anchors = |headings: &[&str]| {
+            let tree = Section::tree(headings.iter().map(|x| IR::header(1, x)));
+            tree.subsections()
+                .iter()
+                .map(|x| x.anchor().unwrap_or("").to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(anchors(&["a", "a", "a-1"]), vec!["a", "a-1", "a-1-1"]);
+        assert_eq!(anchors(&["a-1", "a", "a", "a"]), vec!["a-1", "a", "a-2", "a-3"]);
+    }
+}
@@ -1,14 +1,34 @@
 use ir::TextBlock;
+use ir::IR;
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct TableCell(TextBlock);
+/// A cell of a table, made of blocks so it can hold lists, images and code as
+/// well as text.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TableCell {
+    content: Vec<IR>,
+}
 
 impl TableCell {
+    /// A cell with a single paragraph of text.
     pub fn new(text: TextBlock) -> Self {
-        TableCell(text)
+        TableCell { content: vec![IR::Par(text)] }
+    }
+
+    pub fn blocks(content: Vec<IR>) -> Self {
+        TableCell { content }
     }
 
-    pub fn text(self) -> TextBlock {
-        self.0
+    pub fn iter(&self) -> ::std::slice::Iter<'_, IR> {
+        self.content.iter()
+    }
+}
+
+impl IntoIterator for TableCell {
+    type Item = IR;
+    type IntoIter = ::std::vec::IntoIter<IR>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.content.into_iter()
     }
 }
@@ -4,7 +4,7 @@ pub use self::cell::TableCell;
 mod row;
 pub use self::row::TableRow;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Table {
     header: Option<TableRow>,
     body: Vec<TableRow>,
@@ -39,6 +39,26 @@ impl Table {
         self.clone()
     }
 
+    /// The header, body and footer rows in order.
+    pub fn rows(&self) -> impl Iterator<Item = &TableRow> {
+        self.header
+            .iter()
+            .chain(self.body.iter())
+            .chain(self.footer.iter())
+    }
+
+    pub fn header_ref(&self) -> Option<&TableRow> {
+        self.header.as_ref()
+    }
+
+    pub fn body_ref(&self) -> ::std::slice::Iter<'_, TableRow> {
+        self.body.iter()
+    }
+
+    pub fn footer_ref(&self) -> Option<&TableRow> {
+        self.footer.as_ref()
+    }
+
     pub fn header(&mut self) -> Option<TableRow> {
         self.header.take()
     }
@@ -1,6 +1,7 @@
 use ir::TableCell;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TableRow {
     columns: Vec<TableCell>,
 }
@@ -15,6 +16,10 @@ impl TableRow {
         self
     }
 
+    pub fn iter(&self) -> ::std::slice::Iter<'_, TableCell> {
+        self.columns.iter()
+    }
+
     pub fn build(&self) -> Self {
         self.clone()
     }
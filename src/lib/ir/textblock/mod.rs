@@ -1,7 +1,8 @@
 mod text;
 pub use self::text::Text;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TextBlock {
     content: Vec<Text>,
 }
@@ -31,6 +32,14 @@ impl TextBlock {
     pub fn build(&self) -> Self {
         self.clone()
     }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Text> {
+        self.content.iter()
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.content.iter().all(Text::is_blank)
+    }
 }
 
 impl IntoIterator for TextBlock {
@@ -1,11 +1,27 @@
 use super::TextBlock;
+use ir::FootnoteRef;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum Text {
     Text(String),
     Sub(TextBlock),
     Sup(TextBlock),
     Code(String),
+    Strong(TextBlock),
+    Emphasis(TextBlock),
+    Definition(TextBlock),
+    Cloze(usize, TextBlock),
+    FootnoteRef(FootnoteRef),
+    LineBreak,
+    Keyboard(TextBlock),
+    Variable(TextBlock),
+    Sample(TextBlock),
+    /// An abbreviation, with its expansion if the source gave one.
+    Abbreviation(Option<String>, TextBlock),
+    Deleted(TextBlock),
+    Inserted(TextBlock),
+    Small(TextBlock),
 }
 
 impl Text {
@@ -24,4 +40,74 @@ impl Text {
     pub fn code(code: &str) -> Self {
         Text::Code(code.to_string())
     }
+
+    pub fn strong(text: &str) -> Self {
+        Text::Strong(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn emphasis(text: &str) -> Self {
+        Text::Emphasis(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn definition(text: &str) -> Self {
+        Text::Definition(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn cloze(number: usize, text: &str) -> Self {
+        Text::Cloze(number, TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn footnote_ref(id: &str, label: &str) -> Self {
+        Text::FootnoteRef(FootnoteRef::new(id, label))
+    }
+
+    pub fn keyboard(text: &str) -> Self {
+        Text::Keyboard(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn variable(text: &str) -> Self {
+        Text::Variable(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn sample(text: &str) -> Self {
+        Text::Sample(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn abbreviation(text: &str, title: Option<&str>) -> Self {
+        Text::Abbreviation(title.map(str::to_string),
+                           TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn deleted(text: &str) -> Self {
+        Text::Deleted(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn inserted(text: &str) -> Self {
+        Text::Inserted(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn small(text: &str) -> Self {
+        Text::Small(TextBlock::from(Text::Text(text.to_string())))
+    }
+
+    pub fn is_blank(&self) -> bool {
+        match *self {
+            Text::FootnoteRef(_) => false,
+            Text::LineBreak => true,
+            Text::Text(ref x) | Text::Code(ref x) => x.trim().is_empty(),
+            Text::Sub(ref block) |
+            Text::Sup(ref block) |
+            Text::Strong(ref block) |
+            Text::Emphasis(ref block) |
+            Text::Definition(ref block) |
+            Text::Cloze(_, ref block) |
+            Text::Keyboard(ref block) |
+            Text::Variable(ref block) |
+            Text::Sample(ref block) |
+            Text::Abbreviation(_, ref block) |
+            Text::Deleted(ref block) |
+            Text::Inserted(ref block) |
+            Text::Small(ref block) => block.is_blank(),
+        }
+    }
 }
@@ -0,0 +1,390 @@
+//! Traversal of the IR. A `Visitor` looks at a document by reference and a
+//! `Fold` rebuilds it by value. Every method defaults to visiting the children
+//! through the matching `walk_` or `fold_` function, so an implementation only
+//! overrides the parts it cares about.
+
+use ir::*;
+
+pub trait Visitor {
+    fn visit_document(&mut self, document: &Document) {
+        walk_document(self, document)
+    }
+
+    fn visit_ir(&mut self, ir: &IR) {
+        walk_ir(self, ir)
+    }
+
+    fn visit_image(&mut self, _image: &Image) {}
+
+    fn visit_pre(&mut self, _text: &str) {}
+
+    fn visit_header(&mut self, _level: usize, _text: &str) {}
+
+    fn visit_list(&mut self, list: &List) {
+        walk_list(self, list)
+    }
+
+    fn visit_list_item(&mut self, item: &ListItem) {
+        walk_list_item(self, item)
+    }
+
+    fn visit_table(&mut self, table: &Table) {
+        walk_table(self, table)
+    }
+
+    fn visit_table_row(&mut self, row: &TableRow) {
+        walk_table_row(self, row)
+    }
+
+    fn visit_footnote(&mut self, footnote: &Footnote) {
+        self.visit_text_block(footnote.content_ref())
+    }
+
+    fn visit_text_block(&mut self, block: &TextBlock) {
+        walk_text_block(self, block)
+    }
+
+    fn visit_text(&mut self, text: &Text) {
+        walk_text(self, text)
+    }
+
+    fn visit_footnote_ref(&mut self, _footnote: &FootnoteRef) {}
+}
+
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document) {
+    for ir in document.iter() {
+        visitor.visit_ir(ir);
+    }
+}
+
+pub fn walk_ir<V: Visitor + ?Sized>(visitor: &mut V, ir: &IR) {
+    match *ir {
+        IR::Img(ref image) => visitor.visit_image(image),
+        IR::Pre(ref text) => visitor.visit_pre(text),
+        IR::Par(ref block) => visitor.visit_text_block(block),
+        IR::List(ref list) => visitor.visit_list(list),
+        IR::Table(ref table) => visitor.visit_table(table),
+        IR::Header(level, ref text) => visitor.visit_header(level, text),
+        IR::Footnote(ref footnote) => visitor.visit_footnote(footnote),
+    }
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, list: &List) {
+    for item in list.iter() {
+        visitor.visit_list_item(item);
+    }
+}
+
+pub fn walk_list_item<V: Visitor + ?Sized>(visitor: &mut V, item: &ListItem) {
+    for content in item.iter() {
+        match *content {
+            ListContent::Text(ref block) => visitor.visit_text_block(block),
+            ListContent::List(ref list) => visitor.visit_list(list),
+            ListContent::Block(ref block) => visitor.visit_ir(block),
+        }
+    }
+}
+
+pub fn walk_table<V: Visitor + ?Sized>(visitor: &mut V, table: &Table) {
+    for row in table.rows() {
+        visitor.visit_table_row(row);
+    }
+}
+
+pub fn walk_table_row<V: Visitor + ?Sized>(visitor: &mut V, row: &TableRow) {
+    for block in row.iter().flat_map(TableCell::iter) {
+        visitor.visit_ir(block);
+    }
+}
+
+pub fn walk_text_block<V: Visitor + ?Sized>(visitor: &mut V, block: &TextBlock) {
+    for text in block.iter() {
+        visitor.visit_text(text);
+    }
+}
+
+pub fn walk_text<V: Visitor + ?Sized>(visitor: &mut V, text: &Text) {
+    match *text {
+        Text::Text(_) | Text::Code(_) | Text::LineBreak => (),
+        Text::FootnoteRef(ref footnote) => visitor.visit_footnote_ref(footnote),
+        Text::Sub(ref block) |
+        Text::Sup(ref block) |
+        Text::Strong(ref block) |
+        Text::Emphasis(ref block) |
+        Text::Definition(ref block) |
+        Text::Cloze(_, ref block) |
+        Text::Keyboard(ref block) |
+        Text::Variable(ref block) |
+        Text::Sample(ref block) |
+        Text::Abbreviation(_, ref block) |
+        Text::Deleted(ref block) |
+        Text::Inserted(ref block) |
+        Text::Small(ref block) => visitor.visit_text_block(block),
+    }
+}
+
+pub trait Fold {
+    fn fold_document(&mut self, document: Document) -> Document {
+        fold_document(self, document)
+    }
+
+    fn fold_ir(&mut self, ir: IR) -> IR {
+        fold_ir(self, ir)
+    }
+
+    fn fold_image(&mut self, image: Image) -> Image {
+        image
+    }
+
+    fn fold_pre(&mut self, text: String) -> String {
+        text
+    }
+
+    fn fold_header(&mut self, _level: usize, text: String) -> String {
+        text
+    }
+
+    fn fold_list(&mut self, list: List) -> List {
+        fold_list(self, list)
+    }
+
+    fn fold_list_item(&mut self, item: ListItem) -> ListItem {
+        fold_list_item(self, item)
+    }
+
+    fn fold_table(&mut self, table: Table) -> Table {
+        fold_table(self, table)
+    }
+
+    fn fold_table_row(&mut self, row: TableRow) -> TableRow {
+        fold_table_row(self, row)
+    }
+
+    fn fold_footnote(&mut self, footnote: Footnote) -> Footnote {
+        fold_footnote(self, footnote)
+    }
+
+    fn fold_text_block(&mut self, block: TextBlock) -> TextBlock {
+        fold_text_block(self, block)
+    }
+
+    fn fold_text(&mut self, text: Text) -> Text {
+        fold_text(self, text)
+    }
+}
+
+pub fn fold_document<F: Fold + ?Sized>(folder: &mut F, document: Document) -> Document {
+    let metadata = document.metadata().clone();
+    let origins = document.origins().to_vec();
+
+    document.into_iter()
+        .map(|ir| folder.fold_ir(ir))
+        .collect::<Document>()
+        .set_metadata(metadata)
+        .set_origins(origins)
+}
+
+pub fn fold_ir<F: Fold + ?Sized>(folder: &mut F, ir: IR) -> IR {
+    match ir {
+        IR::Img(image) => IR::Img(folder.fold_image(image)),
+        IR::Pre(text) => IR::Pre(folder.fold_pre(text)),
+        IR::Par(block) => IR::Par(folder.fold_text_block(block)),
+        IR::List(list) => IR::List(folder.fold_list(list)),
+        IR::Table(table) => IR::Table(folder.fold_table(table)),
+        IR::Header(level, text) => IR::Header(level, folder.fold_header(level, text)),
+        IR::Footnote(footnote) => IR::Footnote(folder.fold_footnote(footnote)),
+    }
+}
+
+pub fn fold_list<F: Fold + ?Sized>(folder: &mut F, list: List) -> List {
+    let style = list.style().clone();
+
+    list.into_iter()
+        .map(|item| folder.fold_list_item(item))
+        .fold(&mut List::new(style), |list, item| list.add(item))
+        .build()
+}
+
+pub fn fold_list_item<F: Fold + ?Sized>(folder: &mut F, item: ListItem) -> ListItem {
+    let mut result = ListItem::new();
+    if let Some(value) = item.value() {
+        result.set_value(value);
+    }
+
+    item.into_iter()
+        .map(|content| match content {
+            ListContent::Text(block) => ListContent::Text(folder.fold_text_block(block)),
+            ListContent::List(list) => ListContent::List(folder.fold_list(list)),
+            ListContent::Block(block) => ListContent::Block(folder.fold_ir(block)),
+        })
+        .fold(&mut result, |item, content| item.add(content))
+        .build()
+}
+
+pub fn fold_table<F: Fold + ?Sized>(folder: &mut F, mut table: Table) -> Table {
+    let header = table.header();
+    let footer = table.footer();
+
+    let mut result = Table::new();
+    if let Some(header) = header {
+        result.set_header(folder.fold_table_row(header));
+    }
+    for row in table.body() {
+        result.add(folder.fold_table_row(row));
+    }
+    if let Some(footer) = footer {
+        result.set_footer(folder.fold_table_row(footer));
+    }
+
+    result
+}
+
+pub fn fold_table_row<F: Fold + ?Sized>(folder: &mut F, row: TableRow) -> TableRow {
+    row.into_iter()
+        .map(|cell| {
+            let content = cell.into_iter().map(|ir| folder.fold_ir(ir)).collect();
+
+            TableCell::blocks(content)
+        })
+        .fold(&mut TableRow::new(), |row, cell| row.add(cell))
+        .build()
+}
+
+pub fn fold_footnote<F: Fold + ?Sized>(folder: &mut F, footnote: Footnote) -> Footnote {
+    let id = footnote.id().to_string();
+    let label = footnote.label().to_string();
+
+    Footnote::new(&id, &label, folder.fold_text_block(footnote.content()))
+}
+
+pub fn fold_text_block<F: Fold + ?Sized>(folder: &mut F, block: TextBlock) -> TextBlock {
+    block.into_iter()
+        .map(|text| folder.fold_text(text))
+        .fold(&mut TextBlock::new(), |block, text| block.add(text))
+        .build()
+}
+
+pub fn fold_text<F: Fold + ?Sized>(folder: &mut F, text: Text) -> Text {
+    match text {
+        Text::Sub(block) => Text::Sub(folder.fold_text_block(block)),
+        Text::Sup(block) => Text::Sup(folder.fold_text_block(block)),
+        Text::Strong(block) => Text::Strong(folder.fold_text_block(block)),
+        Text::Emphasis(block) => Text::Emphasis(folder.fold_text_block(block)),
+        Text::Definition(block) => Text::Definition(folder.fold_text_block(block)),
+        Text::Cloze(number, block) => Text::Cloze(number, folder.fold_text_block(block)),
+        Text::Keyboard(block) => Text::Keyboard(folder.fold_text_block(block)),
+        Text::Variable(block) => Text::Variable(folder.fold_text_block(block)),
+        Text::Sample(block) => Text::Sample(folder.fold_text_block(block)),
+        Text::Abbreviation(title, block) => {
+            Text::Abbreviation(title, folder.fold_text_block(block))
+        }
+        Text::Deleted(block) => Text::Deleted(folder.fold_text_block(block)),
+        Text::Inserted(block) => Text::Inserted(folder.fold_text_block(block)),
+        Text::Small(block) => Text::Small(folder.fold_text_block(block)),
+        x => x,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> Document {
+        let text = TextBlock::new()
+            .add(Text::text("a "))
+            .add(Text::Strong(TextBlock::new()
+                .add(Text::text("b"))
+                .add(Text::footnote_ref("n1", "1"))
+                .build()))
+            .build();
+        let list = List::new(ListType::Unordered)
+            .add(ListItem::item_nested_list(TextBlock::from("c"),
+                                            List::new(ListType::ordered())
+                                                .add(ListItem::item(TextBlock::from("d")))
+                                                .build()))
+            .build();
+        let table = Table::new()
+            .set_header(TableRow::new().add(TableCell::new(TextBlock::from("e"))).build())
+            .build();
+
+        Document::new()
+            .add(IR::header(1, "h"))
+            .add(IR::from(text))
+            .add(IR::from(list))
+            .add(IR::from(table))
+            .add(IR::from(Footnote::new("n1", "1", TextBlock::from("f"))))
+    }
+
+    struct Words(Vec<String>);
+
+    impl Visitor for Words {
+        fn visit_header(&mut self, _level: usize, text: &str) {
+            self.0.push(text.to_string());
+        }
+
+        fn visit_text(&mut self, text: &Text) {
+            match *text {
+                Text::Text(ref x) => self.0.push(x.trim().to_string()),
+                Text::FootnoteRef(ref x) => self.0.push(format!("[{}]", x.label())),
+                _ => walk_text(self, text),
+            }
+        }
+    }
+
+    #[test]
+    fn visit() {
+        let mut words = Words(vec![]);
+        words.visit_document(&document());
+
+        assert_eq!(words.0, vec!["h", "a", "b", "[1]", "c", "d", "e", "f"]);
+    }
+
+    struct Upper;
+
+    impl Fold for Upper {
+        fn fold_text(&mut self, text: Text) -> Text {
+            match text {
+                Text::Text(x) => Text::Text(x.to_uppercase()),
+                x => fold_text(self, x),
+            }
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let mut words = Words(vec![]);
+        words.visit_document(&Upper.fold_document(document()));
+
+        assert_eq!(words.0, vec!["h", "A", "B", "[1]", "C", "D", "E", "F"]);
+    }
+
+    #[test]
+    fn fold_header_and_pre() {
+        struct Blocks;
+        impl Fold for Blocks {
+            fn fold_pre(&mut self, text: String) -> String {
+                text.to_uppercase()
+            }
+
+            fn fold_header(&mut self, level: usize, text: String) -> String {
+                format!("{} {}", level, text)
+            }
+        }
+
+        let doc = Document::new()
+            .add(IR::header(2, "h"))
+            .add(IR::pre("p"));
+        let result = Document::new()
+            .add(IR::header(2, "2 h"))
+            .add(IR::pre("P"));
+        assert_eq!(Blocks.fold_document(doc), result);
+    }
+
+    #[test]
+    fn fold_unchanged() {
+        struct Identity;
+        impl Fold for Identity {}
+
+        assert_eq!(Identity.fold_document(document()), document());
+    }
+}
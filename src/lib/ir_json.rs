@@ -0,0 +1,115 @@
+extern crate serde_json;
+
+use ir::Document;
+
+/// The version of the JSON representation. It is bumped whenever the shape of
+/// the IR changes, so tools can tell which documents they understand.
+pub const VERSION: u32 = 8;
+
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u32,
+    document: &'a Document,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct VersionedDocument {
+    document: Document,
+}
+
+pub fn to_json(document: &Document) -> String {
+    let versioned = Versioned {
+        version: VERSION,
+        document,
+    };
+
+    serde_json::to_string_pretty(&versioned).expect("The IR is always valid JSON")
+}
+
+pub fn from_json(contents: &str) -> Result<Document, String> {
+    let version = serde_json::from_str::<Version>(contents)
+        .map_err(|err| format!("Not an IR document: {}", err))?
+        .version;
+    if version != VERSION {
+        return Err(format!("Unsupported IR version {}, expected {}", version, VERSION));
+    }
+
+    serde_json::from_str::<VersionedDocument>(contents)
+        .map(|versioned| versioned.document)
+        .map_err(|err| format!("Invalid IR document: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::*;
+
+    #[test]
+    fn empty() {
+        let doc = Document::new();
+        let result = "{\n  \"version\": 8,\n  \"document\": {\n    \"children\": []\n  }\n}";
+        assert_eq!(to_json(&doc), result);
+    }
+
+    #[test]
+    fn par() {
+        let doc = Document::new().add(IR::from(TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::code("b"))
+            .build()));
+        let json = "{\"version\": 8, \"document\": {\"children\": [\
+                    {\"type\": \"par\", \"value\": [\
+                    {\"type\": \"text\", \"value\": \"a\"}, \
+                    {\"type\": \"code\", \"value\": \"b\"}]}]}}";
+        assert_eq!(from_json(json), Ok(doc));
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = TextBlock::new()
+            .add(Text::strong("a"))
+            .add(Text::Sup(TextBlock::from("2")))
+            .add(Text::cloze(1, "b"))
+            .add(Text::LineBreak)
+            .add(Text::abbreviation("c", Some("d")))
+            .build();
+        let list = List::new(ListType::ordered())
+            .add(ListItem::item_nested_list(text.clone(),
+                                            List::new(ListType::Unordered)
+                                                .add(ListItem::item(text.clone()))
+                                                .build()))
+            .build();
+        let row = TableRow::new()
+            .add(TableCell::new(text.clone()))
+            .build();
+        let table = Table::new()
+            .set_header(row.clone())
+            .add(row)
+            .build();
+        let doc = || {
+            Document::new()
+                .add(IR::header(1, "h"))
+                .add(IR::from(Image::new("a.png")
+                    .set_alt("a")
+                    .set_caption("b")
+                    .build()))
+                .add(IR::pre("x = 1"))
+                .add(IR::from(text.clone()))
+                .add(IR::from(list.clone()))
+                .add(IR::from(table.clone()))
+        };
+
+        assert_eq!(from_json(&to_json(&doc())), Ok(doc()));
+    }
+
+    #[test]
+    fn wrong_version() {
+        let json = "{\"version\": 0, \"document\": {\"children\": []}}";
+        assert!(from_json(json).is_err());
+    }
+}
@@ -1,9 +1,8 @@
 extern crate itertools;
 use self::itertools::join;
 
-use group_lines::group_lines;
-
 use ir::*;
+use ir::visit;
 
 pub trait ToAnki {
     fn to_anki(self) -> String;
@@ -20,10 +19,52 @@ impl ToAnki for Text {
             Text::Code(x) => format!("`{}`", x),
             Text::Sub(block) => format!("_{{{}}}", block.to_anki()),
             Text::Sup(block) => format!("^{{{}}}", block.to_anki()),
+            Text::Strong(block) |
+            Text::Emphasis(block) |
+            Text::Definition(block) => inline(block),
+            Text::Cloze(number, block) => {
+                let content = inline(block);
+                let start = content.len() - content.trim_start().len();
+                let end = content.trim_end().len();
+
+                if start >= end {
+                    return content;
+                }
+
+                format!("{}{{{{c{}::{}}}}}{}",
+                        &content[..start],
+                        number,
+                        escape_cloze(&content[start..end]),
+                        &content[end..])
+            }
+            Text::FootnoteRef(reference) => format!("[{}]", reference.label()),
+            Text::LineBreak => "\n".to_string(),
+            Text::Abbreviation(Some(title), block) => format!("{} ({})", inline(block), title),
+            Text::Deleted(block) => format!("~~{}~~", inline(block)),
+            Text::Keyboard(block) |
+            Text::Variable(block) |
+            Text::Sample(block) |
+            Text::Abbreviation(None, block) |
+            Text::Inserted(block) |
+            Text::Small(block) => inline(block),
         }
     }
 }
 
+/// Renders the children of an inline element without trimming, so the spacing
+/// around it is kept.
+fn inline(block: TextBlock) -> String {
+    block.into_iter()
+        .map(|child| child.to_anki())
+        .collect()
+}
+
+/// Anki ends a cloze at the first `}}` and treats `::` as the start of a hint.
+fn escape_cloze(content: &str) -> String {
+    content.replace("}}", "}&#125;")
+        .replace("::", ":&#58;")
+}
+
 impl ToAnki for TextBlock {
     fn to_anki(self) -> String {
         self.into_iter()
@@ -39,14 +80,40 @@ impl ToAnkiWithDepth for ListContent {
         match self {
             ListContent::Text(x) => x.to_anki(),
             ListContent::List(x) => x.to_anki(depth),
+            ListContent::Block(x) => x.to_anki().trim().to_string(),
         }
     }
 }
 
+/// Indents every line but the first, so a block lines up under the text after
+/// the list marker, which is `2 * depth + 1` wide.
+fn indent(content: &str, depth: usize) -> String {
+    let padding = " ".repeat(2 * depth + 1);
+    let lines = content.lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.is_empty() {
+            line.to_string()
+        } else {
+            format!("{}{}", padding, line)
+        });
+
+    join(lines, "\n")
+}
+
 impl ToAnkiWithDepth for ListItem {
     fn to_anki(self, depth: usize) -> String {
+        let padding = " ".repeat(2 * depth + 1);
         let content = self.into_iter()
-            .map(|content| content.to_anki(depth + 1));
+            .enumerate()
+            .map(|(i, content)| match content {
+                ListContent::List(list) => list.to_anki(depth + 1),
+                // Content after the first line of the item is indented too, so
+                // it is not read as following the list.
+                content if i > 0 => {
+                    format!("{}{}", padding, indent(&content.to_anki(depth + 1), depth))
+                }
+                content => indent(&content.to_anki(depth + 1), depth),
+            });
 
         join(content, "\n")
     }
@@ -54,14 +121,16 @@ impl ToAnkiWithDepth for ListItem {
 
 impl ToAnkiWithDepth for List {
     fn to_anki(self, depth: usize) -> String {
-        match *self.style() {
-            ListType::Ordered => {
+        match self.style().clone() {
+            ListType::Ordered(numbering) => {
                 let indent = String::from("--").repeat(depth - 1);
+                let style = *numbering.style();
 
+                let numbers = self.numbers();
                 let items = self.into_iter()
                     .map(|item| item.to_anki(depth))
-                    .zip(1..)
-                    .map(|(item, num)| format!("{}{}) {}", indent, num, item));
+                    .zip(numbers)
+                    .map(|(item, num)| format!("{}{}) {}", indent, style.format(num), item));
 
                 join(items, "\n")
             }
@@ -79,8 +148,13 @@ impl ToAnkiWithDepth for List {
 }
 
 impl ToAnki for TableCell {
+    /// The blocks of the cell on one line, to keep the rows apart.
     fn to_anki(self) -> String {
-        self.text().to_anki()
+        let blocks = self.into_iter()
+            .map(|block| block.to_anki().trim().replace('\n', " ").replace('|', "\\|"))
+            .filter(|block| !block.is_empty());
+
+        join(blocks, " ")
     }
 }
 
@@ -114,16 +188,42 @@ impl ToAnki for Table {
     }
 }
 
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+impl ToAnki for Image {
+    fn to_anki(self) -> String {
+        let attributes = [("alt", self.alt()),
+                          ("title", self.title()),
+                          ("width", self.width()),
+                          ("height", self.height())];
+        let attributes = attributes.iter()
+            .filter_map(|&(name, value)| value.map(|value| (name, value)))
+            .map(|(name, value)| format!(" {}=\"{}\"", name, escape_attribute(value)))
+            .collect::<String>();
+
+        let img = format!("<img src=\"{}\"{} />", escape_attribute(self.src()), attributes);
+
+        match self.caption() {
+            Some(caption) => format!("{}\n{}", img, caption),
+            None => img,
+        }
+    }
+}
+
 impl ToAnki for IR {
     fn to_anki(self) -> String {
         match self {
-            IR::Img(src) => format!("<img src=\"{}\" />\n", src),
+            IR::Img(image) => format!("{}\n", image.to_anki()),
             IR::Pre(content) => format!("```{}```\n\n", content),
             IR::Par(text) => {
                 let text = text.to_anki();
 
                 if !text.is_empty() {
-                    format!("{}\n\n", group_lines(&text))
+                    format!("{}\n\n", text)
                 } else {
                     format!("")
                 }
@@ -134,16 +234,115 @@ impl ToAnki for IR {
                 let prefix = String::from("#").repeat(level);
                 format!("{} {}\n\n", prefix, text)
             }
+            IR::Footnote(note) => {
+                let label = note.label().to_string();
+                format!("[{}] {}\n", label, note.content().to_anki())
+            }
         }
     }
 }
 
+/// The line crediting where the document came from, linked to its URL when
+/// it is known.
+fn source_line(metadata: &Metadata, html: bool) -> Option<String> {
+    let source = metadata.source()?;
+
+    Some(match metadata.url() {
+        Some(url) => {
+            format!("Source: <a href=\"{}\">{}</a>",
+                    escape_attribute(url),
+                    escape_html(&source))
+        }
+        None if html => format!("Source: {}", escape_html(&source)),
+        None => format!("Source: {}", source),
+    })
+}
+
+/// Footnotes are placed at the end of the note, followed by the source.
 pub fn convert(document: Document) -> String {
-    document.into_iter()
+    convert_with(document, false)
+}
+
+fn convert_with(document: Document, html: bool) -> String {
+    let source = source_line(document.metadata(), html);
+    let (notes, body): (Vec<IR>, Vec<IR>) = document.into_iter()
+        .partition(|x| matches!(*x, IR::Footnote(_)));
+
+    let note = body.into_iter()
+        .chain(notes)
         .map(|x| x.to_anki())
         .collect::<String>()
         .trim()
-        .to_string()
+        .to_string();
+
+    match source {
+        Some(source) if note.is_empty() => source,
+        Some(source) => format!("{}\n\n{}", note, source),
+        None => note,
+    }
+}
+
+/// Escapes the characters that would be read as HTML markup or entities.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes the text of the document that is written out as it is, so that it
+/// is not read as markup in an HTML field.
+struct EscapeHtml;
+
+impl Fold for EscapeHtml {
+    fn fold_pre(&mut self, text: String) -> String {
+        escape_html(&text)
+    }
+
+    fn fold_header(&mut self, _level: usize, text: String) -> String {
+        escape_html(&text)
+    }
+
+    fn fold_image(&mut self, image: Image) -> Image {
+        match image.caption().map(escape_html) {
+            Some(caption) => image.clone().set_caption(&caption).build(),
+            None => image,
+        }
+    }
+
+    fn fold_footnote(&mut self, footnote: Footnote) -> Footnote {
+        let id = footnote.id().to_string();
+        let label = escape_html(footnote.label());
+
+        Footnote::new(&id, &label, self.fold_text_block(footnote.content()))
+    }
+
+    fn fold_text(&mut self, text: Text) -> Text {
+        match text {
+            Text::Text(x) => Text::Text(escape_html(&x)),
+            Text::Code(x) => Text::Code(escape_html(&x)),
+            Text::FootnoteRef(reference) => {
+                Text::footnote_ref(reference.id(), &escape_html(reference.label()))
+            }
+            Text::Abbreviation(title, block) => {
+                Text::Abbreviation(title.map(|x| escape_html(&x)), self.fold_text_block(block))
+            }
+            x => visit::fold_text(self, x),
+        }
+    }
+}
+
+/// Converts the document for a field that is shown as HTML, escaping its text
+/// and keeping the line breaks and indentation that would otherwise collapse.
+pub fn convert_html(document: Document) -> String {
+    let lines = convert_with(EscapeHtml.fold_document(document), true)
+        .lines()
+        .map(|line| {
+            let text = line.trim_start_matches(' ');
+            format!("{}{}", "&nbsp;".repeat(line.len() - text.len()), text)
+        })
+        .collect::<Vec<_>>();
+
+    lines.join("<br>")
 }
 
 #[cfg(test)]
@@ -164,11 +363,49 @@ mod tests {
         assert_eq!(par.to_anki(), result);
     }
 
+    #[test]
+    fn line_break() {
+        let par = IR::from(TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::LineBreak)
+            .add(Text::abbreviation("b", Some("c")))
+            .build());
+        assert_eq!(convert_html(Document::new().add(par)), "a<br>b (c)".to_string());
+    }
+
+    #[test]
+    fn html_escaped() {
+        let doc = Document::new()
+            .add(IR::header(1, "<h>"))
+            .add(IR::from(TextBlock::new()
+                .add(Text::text("a < b & "))
+                .add(Text::code("<div>"))
+                .build()))
+            .add(IR::pre("<p>"));
+        assert_eq!(convert_html(doc),
+                   "# &lt;h&gt;<br><br>a &lt; b &amp; `&lt;div&gt;`<br><br>```&lt;p&gt;```"
+                       .to_string());
+    }
+
+    #[test]
+    fn roman_numbering() {
+        let numbering = Numbering::new()
+            .set_start(3)
+            .set_style(NumberStyle::LowerRoman)
+            .build();
+        let list = List::new(ListType::Ordered(numbering))
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::item(TextBlock::from("b")))
+            .add(ListItem::new().add(ListContent::from("c")).set_value(9).build())
+            .build();
+        assert_eq!(IR::from(list).to_anki(), "iii) a\niv) b\nix) c\n\n".to_string());
+    }
+
     #[test]
     fn ordered_list_single_item() {
         let text = TextBlock::from("a");
         let item = ListItem::item(text);
-        let list = List::new(ListType::Ordered)
+        let list = List::new(ListType::ordered())
             .add(item)
             .build();
         let list = IR::from(list);
@@ -182,7 +419,7 @@ mod tests {
         let item = ListItem::new()
             .add(ListContent::from(text))
             .build();
-        let list = List::new(ListType::Ordered)
+        let list = List::new(ListType::ordered())
             .add(item.clone())
             .add(item.clone())
             .build();
@@ -221,14 +458,14 @@ mod tests {
     #[test]
     fn ordered_list_nested_ordered_list() {
         let text = TextBlock::from("child");
-        let nested_list = List::new(ListType::Ordered)
+        let nested_list = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from(text))
                 .build())
             .build();
 
         let text = TextBlock::from("parent");
-        let list = IR::from(List::new(ListType::Ordered)
+        let list = IR::from(List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from(text))
                 .add(ListContent::from(nested_list))
@@ -248,7 +485,7 @@ mod tests {
             .build();
 
         let text = TextBlock::from("parent");
-        let list = IR::from(List::new(ListType::Ordered)
+        let list = IR::from(List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from(text))
                 .add(ListContent::from(nested_list))
@@ -261,7 +498,7 @@ mod tests {
     #[test]
     fn unordered_list_nested_ordered_list() {
         let text = TextBlock::from("child");
-        let nested_list = List::new(ListType::Ordered)
+        let nested_list = List::new(ListType::ordered())
             .add(ListItem::new()
                 .add(ListContent::from(text))
                 .build())
@@ -298,6 +535,37 @@ mod tests {
         assert_eq!(list.to_anki(), result);
     }
 
+    #[test]
+    fn blocks_in_nested_item() {
+        let nested = List::new(ListType::Unordered)
+            .add(ListItem::new()
+                .add(ListContent::from("b"))
+                .add(ListContent::from(IR::pre("x\ny")))
+                .add(ListContent::from(IR::header(3, "h")))
+                .add(ListContent::from("c"))
+                .build())
+            .build();
+        let list = IR::from(List::new(ListType::ordered())
+            .add(ListItem::item_nested_list(TextBlock::from("a"), nested))
+            .build());
+        let result = "1) a\n---- b\n     ```x\n     y```\n     ### h\n     c\n\n".to_string();
+        assert_eq!(list.clone().to_anki(), result);
+
+        let padding = "&nbsp;".repeat(5);
+        assert_eq!(convert_html(Document::new().add(list)),
+                   format!("1) a<br>---- b<br>{0}```x<br>{0}y```<br>{0}### h<br>{0}c", padding));
+    }
+
+    #[test]
+    fn table_cell_pipe() {
+        let row = TableRow::new()
+            .add(TableCell::new(TextBlock::from("x")))
+            .add(TableCell::new(TextBlock::from("a|b")))
+            .build();
+        let table = IR::from(Table::new().add(row).build());
+        assert_eq!(table.to_anki(), "x | a\\|b\n\n".to_string());
+    }
+
     #[test]
     fn table() {
         let cell_a = TableCell::new(TextBlock::from("a"));
@@ -332,4 +600,71 @@ mod tests {
         let h6 = IR::header(6, "h6");
         assert_eq!(h6.to_anki(), "###### h6\n\n".to_string());
     }
+
+    #[test]
+    fn img() {
+        let img = IR::img("a.png");
+        assert_eq!(img.to_anki(), "<img src=\"a.png\" />\n".to_string());
+
+        let img = IR::from(Image::new("a.png")
+            .set_alt("\"a\"")
+            .set_width("10")
+            .set_caption("Figure 1")
+            .build());
+        assert_eq!(img.to_anki(),
+                   "<img src=\"a.png\" alt=\"&quot;a&quot;\" width=\"10\" />\nFigure 1\n"
+                       .to_string());
+    }
+
+    #[test]
+    fn footnotes() {
+        let doc = Document::new()
+            .add(IR::from(Footnote::new("n1", "1", TextBlock::from("note"))))
+            .add(IR::from(TextBlock::new()
+                .add(Text::text("a"))
+                .add(Text::footnote_ref("n1", "1"))
+                .build()))
+            .add(IR::from(TextBlock::from("b")));
+        assert_eq!(convert(doc), "a[1]\n\nb\n\n[1] note".to_string());
+    }
+
+    #[test]
+    fn strong() {
+        let par = IR::from(TextBlock::new()
+            .add(Text::text("a "))
+            .add(Text::strong("b"))
+            .add(Text::text(" c"))
+            .build());
+        assert_eq!(par.to_anki(), "a b c\n\n".to_string());
+    }
+
+    #[test]
+    fn cloze() {
+        let par = IR::from(TextBlock::new()
+            .add(Text::text("a"))
+            .add(Text::cloze(2, " b "))
+            .add(Text::text("c"))
+            .build());
+        assert_eq!(par.to_anki(), "a {{c2::b}} c\n\n".to_string());
+    }
+
+    #[test]
+    fn cloze_escaped() {
+        let par = IR::from(TextBlock::from(Text::cloze(1, "f::g {{x}}")));
+        assert_eq!(par.to_anki(),
+                   "{{c1::f:&#58;g {{x}&#125;}}\n\n".to_string());
+    }
+
+    #[test]
+    fn source() {
+        let metadata = Metadata::new()
+            .set_title("Cell")
+            .set_url("https://example.com/a?b&c")
+            .build();
+        let doc = Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .set_metadata(metadata);
+        assert_eq!(convert(doc),
+                   "a\n\nSource: <a href=\"https://example.com/a?b&amp;c\">Cell</a>".to_string());
+    }
 }
@@ -0,0 +1,124 @@
+use chunk::{ids, preview, Chunk};
+use hash::hash;
+use ir_to_anki::{convert_html, escape_html};
+
+/// A chunk of a document as a note, with an id that stays the same when the
+/// document is converted again.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Note {
+    id: String,
+    title: String,
+    content: String,
+    tags: Vec<String>,
+}
+
+impl Note {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// A hash of everything written for the note, to tell when it changed.
+    pub fn hash(&self) -> String {
+        let fields = [self.title.as_str(), &self.content, &self.tags.join(" ")].join("\u{1f}");
+
+        format!("{:016x}", hash(fields.as_bytes()))
+    }
+}
+
+/// Makes a note from every chunk of a document. The heading path is the title
+/// and the chunk rendered as for Anki is the text.
+pub fn notes(chunks: Vec<Chunk>, source: &str) -> Vec<Note> {
+    let ids = ids(source, &chunks);
+
+    chunks.into_iter()
+        .zip(ids)
+        .map(|(chunk, id)| {
+            let title = chunk.title().map(|x| escape_html(&x));
+            let document = chunk.document();
+            let tags = document.metadata().tags();
+            let content = convert_html(document);
+            let title = title.unwrap_or_else(|| preview(&content));
+
+            Note {
+                id,
+                title,
+                content,
+                tags,
+            }
+        })
+        .collect()
+}
+
+/// Keeps a field on its line of the file, quoting it if it has quotes.
+fn field(text: &str) -> String {
+    let text = text.replace('\t', " ").replace('\n', "<br>");
+
+    if text.contains('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Writes the notes in the format of Anki's text import, with the id as the
+/// first field. Importing into a note type with Id, Title and Text fields, and
+/// updating existing notes when the first field matches, replaces the notes of
+/// earlier conversions instead of adding them again. The notes go in the deck
+/// if one is given.
+pub fn convert(notes: &[Note], deck: Option<&str>) -> String {
+    let lines = notes.iter()
+        .map(|note| {
+            format!("{}\t{}\t{}\t{}\n",
+                    field(note.id()),
+                    field(note.title()),
+                    field(note.content()),
+                    field(&note.tags().join(" ")))
+        })
+        .collect::<String>();
+
+    let deck = deck.map(|deck| format!("#deck:{}\n", deck.replace(['\t', '\n'], " ")))
+        .unwrap_or_default();
+
+    format!("#separator:tab\n#html:true\n{}#columns:Id\tTitle\tText\tTags\n\
+             #tags column:4\n{}",
+            deck,
+            lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::*;
+
+    #[test]
+    fn notes_file() {
+        let doc = Document::new()
+            .add(IR::header(1, "a"))
+            .add(IR::from(TextBlock::from("say \"b\"")))
+            .set_metadata(Metadata::new().set_url("https://example.com/a").build());
+        let notes = notes(::chunk::chunk(doc), "a");
+        let id = ids("a", &[::chunk::Chunk::new(vec!["a".to_string()])])[0].clone();
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id(), id);
+        assert!(convert(&notes, Some("Reading")).contains("#html:true\n#deck:Reading\n"));
+        assert_eq!(convert(&notes, None),
+                   format!("#separator:tab\n#html:true\n#columns:Id\tTitle\tText\tTags\n\
+                            #tags column:4\n{}\ta\t\"say \"\"b\"\"<br><br>Source: <a \
+                            href=\"\"https://example.com/a\"\">https://example.com/a</a>\"\t\
+                            example.com\n",
+                           id));
+    }
+}
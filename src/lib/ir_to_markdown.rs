@@ -0,0 +1,612 @@
+extern crate itertools;
+use self::itertools::join;
+
+use group_lines::group_lines;
+
+use ir::*;
+
+pub trait ToMarkdown {
+    fn to_markdown(self) -> String;
+}
+
+/// Escapes the characters that Markdown would treat as inline markup.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Escapes the start of a line that would otherwise begin a heading, list,
+/// block quote or thematic break.
+fn escape_line_start(line: &str) -> String {
+    match line.chars().next() {
+        Some('#') | Some('-') | Some('+') | Some('=') => format!("\\{}", line),
+        Some(c) if c.is_ascii_digit() => {
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            match line[digits..].chars().next() {
+                Some('.') | Some(')') => {
+                    format!("{}\\{}", &line[..digits], &line[digits..])
+                }
+                _ => line.to_string(),
+            }
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// The longest run of backticks in the text.
+fn longest_backticks(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Wraps the content in the given delimiters, keeping surrounding whitespace
+/// outside of them since Markdown ignores delimiters next to whitespace.
+fn wrap(content: &str, open: &str, close: &str) -> String {
+    let start = content.len() - content.trim_start().len();
+    let end = content.trim_end().len();
+
+    if start >= end {
+        return content.to_string();
+    }
+
+    format!("{}{}{}{}{}",
+            &content[..start],
+            open,
+            &content[start..end],
+            close,
+            &content[end..])
+}
+
+fn inline(block: TextBlock) -> String {
+    block.into_iter()
+        .map(|child| child.to_markdown())
+        .collect()
+}
+
+impl ToMarkdown for Text {
+    fn to_markdown(self) -> String {
+        match self {
+            Text::Text(x) => escape(&x),
+            Text::Code(x) => {
+                let fence = "`".repeat(longest_backticks(&x) + 1);
+                let padding = if x.starts_with('`') || x.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+
+                format!("{}{}{}{}{}", fence, padding, x, padding, fence)
+            }
+            Text::Sub(block) => format!("<sub>{}</sub>", block.to_markdown()),
+            Text::Sup(block) => format!("<sup>{}</sup>", block.to_markdown()),
+            Text::Strong(block) |
+            Text::Definition(block) => wrap(&inline(block), "**", "**"),
+            Text::Emphasis(block) => wrap(&inline(block), "*", "*"),
+            Text::Cloze(number, block) => {
+                wrap(&inline(block), &format!("{{{{c{}::", number), "}}")
+            }
+            Text::FootnoteRef(reference) => format!("[^{}]", footnote_label(reference.id())),
+            // A backslash before the newline would be lost when the lines of
+            // the paragraph are grouped.
+            Text::LineBreak => "<br>".to_string(),
+            Text::Keyboard(block) => format!("<kbd>{}</kbd>", block.to_markdown()),
+            Text::Variable(block) => wrap(&inline(block), "*", "*"),
+            Text::Sample(block) => format!("<samp>{}</samp>", block.to_markdown()),
+            Text::Abbreviation(Some(title), block) => {
+                format!("<abbr title=\"{}\">{}</abbr>",
+                        title.replace('&', "&amp;").replace('"', "&quot;"),
+                        block.to_markdown())
+            }
+            Text::Abbreviation(None, block) |
+            Text::Small(block) => inline(block),
+            Text::Deleted(block) => wrap(&inline(block), "~~", "~~"),
+            Text::Inserted(block) => format!("<ins>{}</ins>", block.to_markdown()),
+        }
+    }
+}
+
+impl ToMarkdown for TextBlock {
+    fn to_markdown(self) -> String {
+        inline(self)
+            .trim()
+            .to_string()
+    }
+}
+
+/// Footnote labels cannot contain whitespace or brackets, and are renumbered by
+/// most renderers anyway, so the id is used after replacing those.
+fn footnote_label(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_whitespace() || c == '[' || c == ']' || c == '^' {
+            '-'
+        } else {
+            c
+        })
+        .collect()
+}
+
+/// Indents every line but the first, so the content lines up under the list
+/// marker that precedes it.
+fn indent(content: &str, width: usize) -> String {
+    let padding = " ".repeat(width);
+    let lines = content.lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.is_empty() {
+            line.to_string()
+        } else {
+            format!("{}{}", padding, line)
+        });
+
+    join(lines, "\n")
+}
+
+impl ToMarkdown for ListContent {
+    fn to_markdown(self) -> String {
+        match self {
+            ListContent::Text(x) => escape_line_start(&group_lines(&x.to_markdown())),
+            ListContent::List(x) => x.to_markdown(),
+            ListContent::Block(x) => x.to_markdown(),
+        }
+    }
+}
+
+impl ToMarkdown for ListItem {
+    fn to_markdown(self) -> String {
+        let mut result = String::new();
+        let mut previous_text = false;
+        for content in self {
+            // Contents are set apart by blank lines, so that they are read as
+            // paragraphs of their own, except for a list right under the text.
+            let list = matches!(content, ListContent::List(_));
+            if !result.is_empty() {
+                result.push_str(if list && previous_text { "\n" } else { "\n\n" });
+            }
+            previous_text = matches!(content, ListContent::Text(_));
+            result.push_str(&content.to_markdown());
+        }
+
+        result
+    }
+}
+
+impl ToMarkdown for List {
+    fn to_markdown(self) -> String {
+        let numbers = self.numbers();
+        // Markdown only takes the number of the first item and counts up by one
+        // from there, so lists numbered any other way, down, with gaps or not
+        // in decimals, have their numbers written out at the start of bullet
+        // points.
+        let style = match *self.style() {
+            ListType::Ordered(ref numbering) => Some(*numbering.style()),
+            ListType::Unordered => None,
+        };
+        let decimal = style == Some(NumberStyle::Decimal) &&
+                      numbers.first().is_none_or(|&x| x >= 0) &&
+                      numbers.windows(2).all(|x| x[1] == x[0] + 1);
+
+        let items = self.into_iter()
+            .map(|item| item.to_markdown())
+            .zip(numbers)
+            .map(|(item, num)| {
+                match style {
+                    Some(_) if decimal => {
+                        let marker = format!("{}.", num);
+                        format!("{} {}", marker, indent(&item, marker.len() + 1))
+                    }
+                    // The content lines up under the text after the bullet,
+                    // not after the number.
+                    Some(style) => format!("- {}. {}", style.format(num), indent(&item, 2)),
+                    None => format!("- {}", indent(&item, 2)),
+                }
+            });
+
+        join(items, "\n")
+    }
+}
+
+impl ToMarkdown for TableCell {
+    /// A cell has to fit on one line, so its blocks and their lines are
+    /// separated by line breaks.
+    fn to_markdown(self) -> String {
+        let blocks = self.into_iter()
+            .map(|block| match block {
+                IR::Par(text) => group_lines(&text.to_markdown()),
+                // Fences cannot be written on one line, so each line of code is
+                // an inline code span instead.
+                IR::Pre(content) => {
+                    let lines = content.lines()
+                        .map(|line| Text::Code(line.to_string()).to_markdown());
+
+                    join(lines, "<br>").replace('|', "\\|")
+                }
+                block => join(block.to_markdown().replace('|', "\\|").lines(), "<br>"),
+            })
+            .filter(|block| !block.is_empty());
+
+        join(blocks, "<br>")
+    }
+}
+
+fn table_row(cells: &[String], columns: usize) -> String {
+    let cells = (0..columns).map(|i| cells.get(i).map(|cell| cell.as_str()).unwrap_or(""));
+
+    format!("| {} |", join(cells, " | "))
+}
+
+fn row_cells(row: TableRow) -> Vec<String> {
+    row.into_iter()
+        .map(|cell| cell.to_markdown())
+        .collect()
+}
+
+impl ToMarkdown for Table {
+    fn to_markdown(mut self) -> String {
+        let header = self.header().map(row_cells).unwrap_or_default();
+        let footer = self.footer().map(row_cells);
+
+        let mut body = self.body()
+            .into_iter()
+            .map(row_cells)
+            .collect::<Vec<_>>();
+        body.extend(footer);
+
+        let columns = body.iter()
+            .map(|row| row.len())
+            .chain(Some(header.len()))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let delimiter = vec!["---".to_string(); columns];
+        let rows = Some(table_row(&header, columns))
+            .into_iter()
+            .chain(Some(table_row(&delimiter, columns)))
+            .chain(body.iter().map(|row| table_row(row, columns)));
+
+        join(rows, "\n")
+    }
+}
+
+impl ToMarkdown for Image {
+    fn to_markdown(self) -> String {
+        let src = self.src();
+        let src = if src.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+            format!("<{}>", src)
+        } else {
+            src.to_string()
+        };
+        let title = self.title()
+            .map(|title| format!(" \"{}\"", title.replace('"', "\\\"")))
+            .unwrap_or_default();
+        let alt = escape(self.alt().unwrap_or(""));
+
+        let img = format!("![{}]({}{})", alt, src, title);
+
+        match self.caption() {
+            Some(caption) => format!("{}\n{}", img, wrap(&escape(caption), "*", "*")),
+            None => img,
+        }
+    }
+}
+
+impl ToMarkdown for IR {
+    fn to_markdown(self) -> String {
+        match self {
+            IR::Img(image) => image.to_markdown(),
+            IR::Pre(content) => {
+                let fence = "`".repeat(longest_backticks(&content).max(2) + 1);
+                let newline = if content.ends_with('\n') { "" } else { "\n" };
+
+                format!("{}\n{}{}{}", fence, content, newline, fence)
+            }
+            IR::Par(text) => escape_line_start(&group_lines(&text.to_markdown())),
+            IR::List(list) => list.to_markdown(),
+            IR::Table(table) => table.to_markdown(),
+            IR::Header(level, text) => {
+                let prefix = String::from("#").repeat(level);
+                format!("{} {}", prefix, escape(&text))
+            }
+            IR::Footnote(note) => {
+                let label = footnote_label(note.id());
+                format!("[^{}]: {}", label, group_lines(&note.content().to_markdown()))
+            }
+        }
+    }
+}
+
+/// Quotes a value for the YAML front matter.
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The metadata as YAML front matter, or `None` if there is none.
+fn front_matter(metadata: &Metadata) -> Option<String> {
+    if metadata.is_empty() {
+        return None;
+    }
+
+    let fields = [("title", metadata.title()),
+                  ("author", metadata.author()),
+                  ("date", metadata.published()),
+                  ("description", metadata.description()),
+                  ("source", metadata.url()),
+                  ("site", metadata.site_name()),
+                  ("lang", metadata.language())];
+    let mut lines = fields.iter()
+        .filter_map(|&(name, value)| value.map(|value| format!("{}: {}", name, yaml_string(value))))
+        .collect::<Vec<_>>();
+
+    let tags = metadata.tags();
+    if !tags.is_empty() {
+        let tags = tags.iter().map(|x| yaml_string(x)).collect::<Vec<_>>();
+        lines.push(format!("tags: [{}]", tags.join(", ")));
+    }
+
+    Some(format!("---\n{}\n---", lines.join("\n")))
+}
+
+/// Footnotes are placed at the end of the document, which starts with front
+/// matter when its metadata is known.
+pub fn convert(document: Document) -> String {
+    let front_matter = front_matter(document.metadata());
+    let (notes, body): (Vec<IR>, Vec<IR>) = document.into_iter()
+        .partition(|x| matches!(*x, IR::Footnote(_)));
+
+    let blocks = body.into_iter()
+        .chain(notes)
+        .map(|x| x.to_markdown())
+        .filter(|x| !x.is_empty());
+
+    join(front_matter.into_iter().chain(blocks), "\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let doc = Document::new();
+        assert_eq!(convert(doc), "".to_string());
+    }
+
+    #[test]
+    fn par() {
+        let par = IR::from(TextBlock::from("a"));
+        assert_eq!(par.to_markdown(), "a".to_string());
+    }
+
+    #[test]
+    fn par_escaped() {
+        let par = IR::from(TextBlock::from("# a *b* [c](d) x|y"));
+        assert_eq!(par.to_markdown(),
+                   "\\# a \\*b\\* \\[c\\](d) x\\|y".to_string());
+
+        let par = IR::from(TextBlock::from("1. a"));
+        assert_eq!(par.to_markdown(), "1\\. a".to_string());
+    }
+
+    #[test]
+    fn inline() {
+        let par = IR::from(TextBlock::new()
+            .add(Text::strong("a "))
+            .add(Text::emphasis("b"))
+            .add(Text::text(" "))
+            .add(Text::code("c`d"))
+            .add(Text::sup("2"))
+            .build());
+        assert_eq!(par.to_markdown(),
+                   "**a** *b* ``c`d``<sup>2</sup>".to_string());
+    }
+
+    #[test]
+    fn semantic_inline() {
+        let par = IR::from(TextBlock::new()
+            .add(Text::keyboard("Ctrl"))
+            .add(Text::LineBreak)
+            .add(Text::abbreviation("HTML", Some("Hyper\"Text\"")))
+            .add(Text::text(" "))
+            .add(Text::deleted("old"))
+            .add(Text::text(" "))
+            .add(Text::small("fine print"))
+            .build());
+        assert_eq!(par.to_markdown(),
+                   "<kbd>Ctrl</kbd><br><abbr title=\"Hyper&quot;Text&quot;\">HTML</abbr> ~~old~~ \
+                    fine print"
+                       .to_string());
+    }
+
+    #[test]
+    fn pre() {
+        let pre = IR::pre("x = 1\n```");
+        assert_eq!(pre.to_markdown(), "````\nx = 1\n```\n````".to_string());
+    }
+
+    #[test]
+    fn ordered_list() {
+        let list = IR::from(List::new(ListType::ordered())
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::item(TextBlock::from("b")))
+            .build());
+        assert_eq!(list.to_markdown(), "1. a\n2. b".to_string());
+    }
+
+    #[test]
+    fn unordered_list_nested_ordered_list() {
+        let nested_list = List::new(ListType::ordered())
+            .add(ListItem::item(TextBlock::from("child")))
+            .add(ListItem::item(TextBlock::from("child")))
+            .build();
+        let list = IR::from(List::new(ListType::Unordered)
+            .add(ListItem::item_nested_list(TextBlock::from("parent"), nested_list))
+            .build());
+        assert_eq!(list.to_markdown(),
+                   "- parent\n  1. child\n  2. child".to_string());
+    }
+
+    #[test]
+    fn ordered_list_nested_unordered_list() {
+        let nested_list = List::new(ListType::Unordered)
+            .add(ListItem::item(TextBlock::from("child")))
+            .build();
+        let list = IR::from(List::new(ListType::ordered())
+            .add(ListItem::item_nested_list(TextBlock::from("parent"), nested_list))
+            .build());
+        assert_eq!(list.to_markdown(), "1. parent\n   - child".to_string());
+    }
+
+    #[test]
+    fn numbering() {
+        let reversed = Numbering::new().set_reversed(true).build();
+        let list = IR::from(List::new(ListType::Ordered(reversed))
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::item(TextBlock::from("b")))
+            .build());
+        assert_eq!(list.to_markdown(), "- 2. a\n- 1. b".to_string());
+
+        let start = Numbering::new().set_reversed(true).set_start(3).build();
+        let list = IR::from(List::new(ListType::Ordered(start))
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::item(TextBlock::from("b")))
+            .add(ListItem::item(TextBlock::from("c")))
+            .build());
+        assert_eq!(list.to_markdown(), "- 3. a\n- 2. b\n- 1. c".to_string());
+
+        let list = IR::from(List::new(ListType::ordered())
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::new().add(ListContent::from("b")).set_value(5).build())
+            .add(ListItem::item(TextBlock::from("c")))
+            .build());
+        assert_eq!(list.to_markdown(), "- 1. a\n- 5. b\n- 6. c".to_string());
+
+        let start = Numbering::new().set_start(4).build();
+        let list = IR::from(List::new(ListType::Ordered(start))
+            .add(ListItem::item(TextBlock::from("a")))
+            .add(ListItem::new().add(ListContent::from("b")).set_value(5).build())
+            .build());
+        assert_eq!(list.to_markdown(), "4. a\n5. b".to_string());
+
+        let alpha = Numbering::new().set_style(NumberStyle::UpperAlpha).build();
+        let list = IR::from(List::new(ListType::Ordered(alpha))
+            .add(ListItem::item(TextBlock::from("a")))
+            .build());
+        assert_eq!(list.to_markdown(), "- A. a".to_string());
+    }
+
+    #[test]
+    fn table() {
+        let row = TableRow::new()
+            .add(TableCell::new(TextBlock::from("a")))
+            .add(TableCell::new(TextBlock::from("b|c")))
+            .build();
+        let table = IR::from(Table::new()
+            .set_header(row.clone())
+            .add(row.clone())
+            .set_footer(row)
+            .build());
+        let result = "| a | b\\|c |\n| --- | --- |\n| a | b\\|c |\n| a | b\\|c |";
+        assert_eq!(table.to_markdown(), result.to_string());
+    }
+
+    #[test]
+    fn blocks_in_cells_and_items() {
+        let cell = TableCell::blocks(vec![IR::from(TextBlock::from("a")), IR::pre("b|c\nd")]);
+        let table = IR::from(Table::new()
+            .add(TableRow::new().add(cell).build())
+            .build());
+        assert_eq!(table.to_markdown(), "|  |\n| --- |\n| a<br>`b\\|c`<br>`d` |".to_string());
+
+        let item = ListItem::new()
+            .add(ListContent::from("a"))
+            .add(ListContent::from(IR::pre("b")))
+            .add(ListContent::from("c"))
+            .build();
+        let list = IR::from(List::new(ListType::Unordered).add(item).build());
+        assert_eq!(list.to_markdown(), "- a\n\n  ```\n  b\n  ```\n\n  c".to_string());
+    }
+
+    #[test]
+    fn paragraphs_in_items() {
+        let item = ListItem::new()
+            .add(ListContent::from("a"))
+            .add(ListContent::from("b"))
+            .build();
+        let list = IR::from(List::new(ListType::ordered()).add(item).build());
+        assert_eq!(list.to_markdown(), "1. a\n\n   b".to_string());
+    }
+
+    #[test]
+    fn table_without_header() {
+        let row = TableRow::new()
+            .add(TableCell::new(TextBlock::from("a")))
+            .build();
+        let table = IR::from(Table::new()
+            .add(row)
+            .build());
+        assert_eq!(table.to_markdown(), "|  |\n| --- |\n| a |".to_string());
+    }
+
+    #[test]
+    fn header() {
+        let h2 = IR::header(2, "a_b");
+        assert_eq!(h2.to_markdown(), "## a\\_b".to_string());
+    }
+
+    #[test]
+    fn img() {
+        let img = IR::from(Image::new("a.png")
+            .set_alt("a [b]")
+            .set_title("t \"q\"")
+            .set_caption("Figure 1")
+            .build());
+        assert_eq!(img.to_markdown(),
+                   "![a \\[b\\]](a.png \"t \\\"q\\\"\")\n*Figure 1*".to_string());
+    }
+
+    #[test]
+    fn footnotes() {
+        let doc = Document::new()
+            .add(IR::from(Footnote::new("fn 1", "1", TextBlock::from("note"))))
+            .add(IR::from(TextBlock::new()
+                .add(Text::text("a"))
+                .add(Text::footnote_ref("fn 1", "1"))
+                .build()));
+        assert_eq!(convert(doc), "a[^fn-1]\n\n[^fn-1]: note".to_string());
+    }
+
+    #[test]
+    fn document() {
+        let doc = Document::new()
+            .add(IR::header(1, "h"))
+            .add(IR::from(TextBlock::from("")))
+            .add(IR::img("a b.png"));
+        assert_eq!(convert(doc), "# h\n\n![](<a b.png>)".to_string());
+    }
+
+    #[test]
+    fn front_matter() {
+        let metadata = Metadata::new()
+            .set_title("The \"Cell\"")
+            .set_url("https://en.example.org/cell")
+            .set_language("en")
+            .build();
+        let doc = Document::new()
+            .add(IR::from(TextBlock::from("a")))
+            .set_metadata(metadata);
+        assert_eq!(convert(doc),
+                   "---\ntitle: \"The \\\"Cell\\\"\"\nsource: \"https://en.example.org/cell\"\n\
+                    lang: \"en\"\ntags: [\"en.example.org\", \"lang:en\"]\n---\n\na".to_string());
+    }
+}
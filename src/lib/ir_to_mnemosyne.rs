@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use chunk::{chunk_with, ids, preview, source, Chunk};
+use ir::Document;
+use ir_to_anki::{convert_html, escape_html};
+
+const CATEGORY: &str = "Incremental Reading";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Makes an item from every chunk of the document, in Mnemosyne's XML import
+/// format. The heading path is the question and the chunk rendered as for
+/// Anki is the answer.
+pub fn convert(document: Document) -> String {
+    convert_with(document, None)
+}
+
+/// Converts the document with at most `max_sentences` sentences in an item.
+pub fn convert_with(document: Document, max_sentences: Option<usize>) -> String {
+    let source = source(&document, Path::new(""));
+
+    convert_chunks(chunk_with(document, max_sentences), &source, None)
+}
+
+/// Makes an item from every chunk, in the category if one is given. The ids
+/// of the items are made from the source as for Anki notes, so they stay the
+/// same when the document is converted again.
+pub fn convert_chunks(chunks: Vec<Chunk>, source: &str, category: Option<&str>) -> String {
+    let category = escape(category.unwrap_or(CATEGORY));
+    let ids = ids(source, &chunks);
+    let items = chunks.into_iter()
+        .zip(ids)
+        .map(|(chunk, id)| {
+            let title = chunk.title().map(|x| escape_html(&x));
+            let answer = convert_html(chunk.document());
+            let question = title.unwrap_or_else(|| preview(&answer));
+
+            format!("<item id=\"{}\">\n<cat>{}</cat>\n<Q>{}</Q>\n<A>{}</A>\n</item>\n",
+                    id,
+                    category,
+                    escape(&question),
+                    escape(&answer))
+        })
+        .collect::<String>();
+
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <mnemosyne core_version=\"1\">\n\
+             <category active=\"1\">\n<name>{}</name>\n</category>\n\
+             {}</mnemosyne>\n",
+            category,
+            items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::*;
+
+    #[test]
+    fn empty() {
+        let result = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                      <mnemosyne core_version=\"1\">\n\
+                      <category active=\"1\">\n<name>Incremental Reading</name>\n</category>\n\
+                      </mnemosyne>\n";
+        assert_eq!(convert(Document::new()), result.to_string());
+    }
+
+    #[test]
+    fn escaped() {
+        let doc = Document::new()
+            .add(IR::header(1, "a & b"))
+            .add(IR::img("x.png"))
+            .add(IR::from(TextBlock::from("<c>")));
+        let result = convert(doc);
+        assert!(result.contains("<Q>a &amp;amp; b</Q>\n\
+                                 <A>&lt;img src=&quot;x.png&quot; /&gt;&lt;br&gt;\
+                                 &amp;lt;c&amp;gt;</A>"));
+    }
+}
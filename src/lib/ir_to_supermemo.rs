@@ -0,0 +1,51 @@
+use chunk::{chunk_with, preview, Chunk};
+use ir::Document;
+use ir_to_anki::{convert_html, escape_html};
+
+/// Makes a Q&A item from every chunk of the document, in the text format read
+/// by SuperMemo's Q&A import. The heading path is the question and the chunk
+/// rendered as for Anki is the answer.
+pub fn convert(document: Document) -> String {
+    convert_with(document, None)
+}
+
+/// Converts the document with at most `max_sentences` sentences in an item.
+pub fn convert_with(document: Document, max_sentences: Option<usize>) -> String {
+    convert_chunks(chunk_with(document, max_sentences))
+}
+
+/// Makes an item from every chunk.
+pub fn convert_chunks(chunks: Vec<Chunk>) -> String {
+    chunks.into_iter()
+        .map(|chunk| {
+            let title = chunk.title().map(|x| escape_html(&x));
+            let answer = convert_html(chunk.document());
+            let question = title.unwrap_or_else(|| preview(&answer));
+
+            format!("Q: {}\nA: {}\n", question, answer)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(convert(Document::new()), "".to_string());
+    }
+
+    #[test]
+    fn chunks() {
+        let doc = Document::new()
+            .add(IR::from(TextBlock::from("intro")))
+            .add(IR::header(1, "a"))
+            .add(IR::from(TextBlock::from("b")))
+            .add(IR::from(TextBlock::from("c")));
+        let result = "Q: intro\nA: intro\n\nQ: a\nA: b<br><br>c\n".to_string();
+        assert_eq!(convert(doc), result);
+    }
+}
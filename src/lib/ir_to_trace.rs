@@ -0,0 +1,67 @@
+use chunk::preview;
+use ir::{Document, IR, Origin};
+use ir_to_markdown::ToMarkdown;
+
+/// Writes every block as Markdown under a comment with the line and tag of
+/// the input it came from, to find what produced a wrong part of the output.
+/// The blocks nested in lists and tables follow in comments of their own,
+/// indented by how deep they are and with the start of their text.
+pub fn convert(document: Document) -> String {
+    document.iter()
+        .enumerate()
+        .map(|(index, block)| {
+            let origin = document.origin(index);
+            let mut comments = vec![format!("<!-- {} -->", describe(origin))];
+            nested(block, origin, 1, &mut comments);
+
+            format!("{}\n{}", comments.join("\n"), block.clone().to_markdown())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn describe(origin: Option<&Origin>) -> String {
+    match origin {
+        Some(origin) => format!("line {}: <{}>", origin.line(), origin.tag()),
+        None => "unknown origin".to_string(),
+    }
+}
+
+fn nested(block: &IR, origin: Option<&Origin>, depth: usize, comments: &mut Vec<String>) {
+    let origins = origin.map_or(&[][..], Origin::nested);
+    for (index, block) in block.nested().into_iter().enumerate() {
+        let origin = origins.get(index).and_then(Option::as_ref);
+        comments.push(format!("<!-- {}{}: {} -->",
+                              "  ".repeat(depth),
+                              describe(origin),
+                              preview(&block.clone().to_markdown().replace("```", ""))));
+        nested(block, origin, depth + 1, comments);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ir::*;
+
+    #[test]
+    fn origins() {
+        let doc = Document::new()
+            .add_from(IR::header(1, "a"), Some(Origin::new("h1", 3)))
+            .add(IR::from(TextBlock::from("b")));
+        assert_eq!(convert(doc),
+                   "<!-- line 3: <h1> -->\n# a\n\n<!-- unknown origin -->\nb".to_string());
+    }
+
+    #[test]
+    fn nested_origins() {
+        let item = ListItem::new()
+            .add(ListContent::from(TextBlock::from("a")))
+            .add(ListContent::from(IR::pre("b")))
+            .build();
+        let origin = Origin::new("ul", 1).set_nested(vec![Some(Origin::new("pre", 2))]);
+        let doc = Document::new()
+            .add_from(IR::from(List::new(ListType::Unordered).add(item).build()), Some(origin));
+        assert!(convert(doc).starts_with("<!-- line 1: <ul> -->\n<!--   line 2: <pre>: b -->"));
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use hash::hash;
+use ir::{Document, Fold, Image};
+
+/// Copies the images referenced by a document into a flat media directory,
+/// such as Anki's `collection.media`, and rewrites the document to refer to
+/// the copies.
+pub struct Media {
+    base: PathBuf,
+    output: PathBuf,
+}
+
+impl Media {
+    /// Relative image paths are resolved against `base`, which is normally the
+    /// directory of the input file. Images are copied into `output`.
+    pub fn new(base: &Path, output: &Path) -> Self {
+        Media {
+            base: base.to_path_buf(),
+            output: output.to_path_buf(),
+        }
+    }
+
+    /// Copies every image of the document and rewrites its source to the name
+    /// of the copy. Images that cannot be found, and remote images, are left
+    /// as they are.
+    pub fn collect(&self, document: Document) -> io::Result<Document> {
+        fs::create_dir_all(&self.output)?;
+
+        let mut collector = Collector {
+            media: self,
+            error: None,
+        };
+        let document = collector.fold_document(document);
+
+        match collector.error {
+            Some(err) => Err(err),
+            None => Ok(document),
+        }
+    }
+
+    fn image(&self, src: &str) -> io::Result<Option<String>> {
+        let (stem, extension, contents) = match self.load(src) {
+            Some(image) => image,
+            None => return Ok(None),
+        };
+
+        let name = format!("{}-{:016x}.{}", stem, hash(&contents), extension);
+        let path = self.output.join(&name);
+        if !path.exists() {
+            File::create(&path)?.write_all(&contents)?;
+        }
+
+        Ok(Some(name))
+    }
+
+    /// Reads the image, returning a name and extension for it and its contents.
+    fn load(&self, src: &str) -> Option<(String, String, Vec<u8>)> {
+        if src.starts_with("data:") {
+            let image = parse_data_uri(src);
+            if image.is_none() {
+                warn!("Could not decode data URI image");
+            }
+
+            return image.map(|(extension, contents)| ("image".to_string(), extension, contents));
+        }
+
+        if src.contains("://") && !src.starts_with("file://") {
+            info!("Leaving remote image {} as it is", src);
+            return None;
+        }
+
+        let path = self.resolve(src);
+        let mut contents = vec![];
+        if let Err(err) = File::open(&path).and_then(|mut file| file.read_to_end(&mut contents)) {
+            warn!("Could not read image {}: {}", path.display(), err);
+            return None;
+        }
+
+        let stem = path.file_stem()
+            .map(|stem| sanitize(&stem.to_string_lossy()))
+            .unwrap_or_else(|| "image".to_string());
+        let extension = path.extension()
+            .map(|extension| sanitize(&extension.to_string_lossy()).to_lowercase())
+            .unwrap_or_else(|| "img".to_string());
+
+        Some((stem, extension, contents))
+    }
+
+    fn resolve(&self, src: &str) -> PathBuf {
+        let src = src.trim_start_matches("file://");
+        let src = src.split(['?', '#']).next().unwrap_or("");
+        let path = PathBuf::from(percent_decode(src));
+
+        if path.is_absolute() {
+            path
+        } else {
+            self.base.join(path)
+        }
+    }
+}
+
+/// Copies the images wherever they are in the document, such as in list items
+/// and table cells, keeping the first error.
+struct Collector<'a> {
+    media: &'a Media,
+    error: Option<io::Error>,
+}
+
+impl<'a> Fold for Collector<'a> {
+    fn fold_image(&mut self, mut image: Image) -> Image {
+        if self.error.is_some() {
+            return image;
+        }
+
+        match self.media.image(image.src()) {
+            Ok(Some(name)) => {
+                image.set_src(&name);
+            }
+            Ok(None) => (),
+            Err(err) => self.error = Some(err),
+        }
+
+        image
+    }
+}
+
+/// Keeps only the characters that are safe in a file name on every platform.
+fn sanitize(name: &str) -> String {
+    let name = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+            c
+        } else {
+            '_'
+        })
+        .collect::<String>();
+
+    if name.is_empty() {
+        "image".to_string()
+    } else {
+        name
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some(u32::from(c - b'A')),
+            b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+            b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+            b'+' | b'-' => Some(62),
+            b'/' | b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    let mut decoded = vec![];
+    let mut buffer = 0;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        buffer = (buffer << 6) | value(c)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Decodes a `data:` URI into the file extension for its media type and its
+/// contents.
+fn parse_data_uri(uri: &str) -> Option<(String, Vec<u8>)> {
+    let uri = uri.trim_start_matches("data:");
+    let comma = uri.find(',')?;
+    let (header, data) = (&uri[..comma], &uri[comma + 1..]);
+
+    let mut parameters = header.split(';');
+    let media_type = parameters.next().unwrap_or("").to_lowercase();
+    let base64 = parameters.any(|parameter| parameter == "base64");
+
+    let extension = match media_type.as_str() {
+        "image/jpeg" | "image/jpg" => "jpg".to_string(),
+        "image/svg+xml" => "svg".to_string(),
+        "" => "img".to_string(),
+        _ => {
+            let subtype = media_type.split('/').nth(1).unwrap_or("");
+            sanitize(subtype)
+        }
+    };
+
+    let contents = if base64 {
+        base64_decode(&percent_decode(data))?
+    } else {
+        percent_decode(data).into_bytes()
+    };
+
+    Some((extension, contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use html_to_ir;
+    use ir::*;
+    use std::env;
+
+    #[test]
+    fn data_uri_base64() {
+        let uri = "data:image/png;base64,aGVsbG8=";
+        assert_eq!(parse_data_uri(uri), Some(("png".to_string(), b"hello".to_vec())));
+    }
+
+    #[test]
+    fn data_uri_percent_encoded() {
+        let uri = "data:image/svg+xml,%3Csvg%3E";
+        assert_eq!(parse_data_uri(uri), Some(("svg".to_string(), b"<svg>".to_vec())));
+    }
+
+    #[test]
+    fn data_uri_invalid() {
+        assert_eq!(parse_data_uri("data:image/png;base64"), None);
+        assert_eq!(parse_data_uri("data:image/png;base64,a!b"), None);
+    }
+
+    #[test]
+    fn resolve() {
+        let media = Media::new(Path::new("/in"), Path::new("/out"));
+        assert_eq!(media.resolve("../images/a%20b.png?v=2"),
+                   PathBuf::from("/in/../images/a b.png"));
+        assert_eq!(media.resolve("file:///x/y.png"), PathBuf::from("/x/y.png"));
+    }
+
+    #[test]
+    fn collect() {
+        let dir = env::temp_dir().join(format!("anki_convert_media_{}", ::std::process::id()));
+        let input = dir.join("in");
+        let output = dir.join("out");
+        fs::create_dir_all(input.join("images")).unwrap();
+        File::create(input.join("images/a b.png")).unwrap().write_all(b"png").unwrap();
+
+        let doc = Document::new()
+            .add(IR::img("images/a%20b.png"))
+            .add(IR::img("data:image/gif;base64,Z2lm"))
+            .add(IR::img("missing.png"))
+            .add(IR::img("http://example.com/a.png"));
+
+        let media = Media::new(&input, &output);
+        let result = Document::new()
+            .add(IR::img(&format!("a_b-{:016x}.png", hash(b"png"))))
+            .add(IR::img(&format!("image-{:016x}.gif", hash(b"gif"))))
+            .add(IR::img("missing.png"))
+            .add(IR::img("http://example.com/a.png"));
+        assert_eq!(media.collect(doc).unwrap(), result);
+
+        let mut contents = String::new();
+        File::open(output.join(format!("a_b-{:016x}.png", hash(b"png"))))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "png");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn collect_nested() {
+        let dir = env::temp_dir().join(format!("anki_convert_nested_{}", ::std::process::id()));
+        let input = dir.join("in");
+        fs::create_dir_all(&input).unwrap();
+        File::create(input.join("a.png")).unwrap().write_all(b"png").unwrap();
+
+        let doc = html_to_ir::convert_file("<ul><li>x<img src=\"a.png\"></li></ul>\
+                                            <table><tr><td><img src=\"a.png\"></td></tr></table>");
+        let name = format!("a-{:016x}.png", hash(b"png"));
+        let item = ListItem::new()
+            .add(ListContent::from(TextBlock::from("x")))
+            .add(ListContent::from(IR::img(&name)))
+            .build();
+        let row = TableRow::new().add(TableCell::blocks(vec![IR::img(&name)])).build();
+        let result = Document::new()
+            .add(IR::from(List::new(ListType::Unordered).add(item).build()))
+            .add(IR::from(Table::new().add(row).build()));
+        let collected = Media::new(&input, &dir.join("out")).collect(doc).unwrap();
+        assert_eq!(collected, result);
+        assert_eq!(collected.origins().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}